@@ -3,15 +3,14 @@ use crate::{Speaker, GuildSpeakerRef, GuildSpeakerHandle, SongMetadata};
 use serenity::model::prelude::*;
 use futures::prelude::*;
 
+#[derive(Default)]
 pub struct Brain {
     pub speakers: Vec<Arc<Speaker>>,
 }
 
 impl Brain {
     pub fn new() -> Self {
-        Brain {
-            speakers: Vec::new(),
-        }
+        Brain::default()
     }
 
     pub fn guild_speakers(&self, guild_id: GuildId) -> BrainSpeakersHandle {
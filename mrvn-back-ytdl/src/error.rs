@@ -1,8 +1,10 @@
 #[derive(Debug)]
 pub enum Error {
     Runtime(tokio::task::JoinError),
+    Io(std::io::Error),
+    Json(serde_json::Error),
     SongbirdInput(songbird::input::error::Error),
-    SongbirdJoin(songbird::error::JoinError),
+    SongbirdJoin(Box<songbird::error::JoinError>),
     SongbirdTrack(songbird::error::TrackError),
 
     NoSongsFound,
@@ -13,6 +15,8 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Runtime(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+            Error::Json(err) => err.fmt(f),
             Error::SongbirdInput(err) => err.fmt(f),
             Error::SongbirdJoin(err) => err.fmt(f),
             Error::SongbirdTrack(err) => err.fmt(f),
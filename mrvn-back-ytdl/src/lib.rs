@@ -1,9 +1,15 @@
 mod brain;
 mod error;
+mod library;
+mod resolver;
 mod song;
+mod source;
 mod speaker;
 
 pub use self::brain::*;
 pub use self::error::*;
+pub use self::library::*;
+pub use self::resolver::*;
 pub use self::song::*;
+pub use self::source::*;
 pub use self::speaker::*;
@@ -0,0 +1,301 @@
+use crate::{probe, Error, ResolverKind, Song, SongMetadata, AUDIO_FILE_EXTENSIONS};
+use futures::prelude::*;
+use serde::{Serialize, Deserialize};
+use serenity::model::prelude::UserId;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How many files are read with ffprobe at once while scanning.
+const PROBE_CONCURRENCY: usize = 8;
+
+/// The average score each word in a search needs for a track to be played instead of searching
+/// online, i.e. words should mostly match whole words in the track's tags. See
+/// [`LibraryTrack::match_score`].
+const MIN_MATCH_SCORE_PER_WORD: usize = 2;
+
+/// An audio file found in one of the library's directories.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LibraryTrack {
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+
+    /// When the file was last modified, in seconds since the Unix epoch. Files are only read
+    /// again when this changes.
+    modified_secs: u64,
+}
+
+impl LibraryTrack {
+    pub fn song(&self, user_id: UserId) -> Option<Song> {
+        let url = url::Url::from_file_path(&self.path).ok()?.to_string();
+        let title = match &self.artist {
+            Some(artist) => format!("{} - {}", artist, self.title),
+            None => self.title.clone(),
+        };
+
+        Some(Song {
+            metadata: SongMetadata {
+                title,
+                url: url.clone(),
+                user_id,
+                duration: self.duration,
+            },
+            source_term: url,
+            resolver: ResolverKind::File,
+        })
+    }
+
+    /// Scores how well the words match the track's tags. Each word scores 3 if it's a whole word
+    /// in the title, 2 if it's a whole word in the artist or album, and 1 if it only starts a word.
+    /// Returns `None` if any word doesn't match at all.
+    fn match_score(&self, words: &[String]) -> Option<usize> {
+        let title_words = split_words(&self.title);
+        let tag_words: Vec<_> = [&self.artist, &self.album]
+            .iter()
+            .filter_map(|tag| tag.as_deref())
+            .flat_map(split_words)
+            .collect();
+
+        words
+            .iter()
+            .map(|word| {
+                if title_words.contains(word) {
+                    Some(3)
+                } else if tag_words.contains(word) {
+                    Some(2)
+                } else if title_words.iter().chain(&tag_words).any(|tag_word| tag_word.starts_with(word.as_str())) {
+                    Some(1)
+                } else {
+                    None
+                }
+            })
+            .sum()
+    }
+}
+
+/// An index of audio files in a set of directories. The index is kept in a file so files that
+/// haven't changed don't need to be read again after a restart.
+pub struct Library {
+    directories: Vec<PathBuf>,
+
+    /// The directories with symlinks and `..` resolved, so paths can be checked against them
+    /// without touching the file system. These are updated on each scan, in case a directory
+    /// didn't exist before.
+    canonical_directories: RwLock<Vec<PathBuf>>,
+    index_path: Option<PathBuf>,
+    tracks: RwLock<Vec<LibraryTrack>>,
+}
+
+impl Library {
+    pub fn load(directories: Vec<PathBuf>, index_path: Option<PathBuf>) -> Result<Library, Error> {
+        let tracks = match &index_path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(tracks_string) => serde_json::from_str(&tracks_string).map_err(Error::Json)?,
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(why) => return Err(Error::Io(why)),
+            },
+            None => Vec::new(),
+        };
+
+        Ok(Library {
+            canonical_directories: RwLock::new(canonicalize_directories(&directories)),
+            directories,
+            index_path,
+            tracks: RwLock::new(tracks),
+        })
+    }
+
+    pub fn has_directories(&self) -> bool {
+        !self.directories.is_empty()
+    }
+
+    /// Finds the track that best matches a search term, preferring tracks with more of the
+    /// term's words in their title. Returns `None` if no track matches closely enough, so the
+    /// term can be searched for online instead.
+    pub fn search(&self, term: &str, user_id: UserId) -> Option<Song> {
+        let words = split_words(term);
+        if words.is_empty() || url::Url::parse(term).is_ok() {
+            return None;
+        }
+
+        let min_score = words.len() * MIN_MATCH_SCORE_PER_WORD;
+        let tracks = self.tracks.read().unwrap();
+        tracks
+            .iter()
+            .filter_map(|track| track.match_score(&words).map(|score| (score, track)))
+            .filter(|(score, _)| *score >= min_score)
+            .max_by(|(a_score, a_track), (b_score, b_track)| {
+                a_score.cmp(b_score).then_with(|| b_track.title.len().cmp(&a_track.title.len()))
+            })
+            .and_then(|(_, track)| track.song(user_id))
+    }
+
+    /// Lists up to `limit` tracks that match every word of a partly typed search term, best
+    /// matches first. Unlike [`Library::search`], words only need to start a word in the tags.
+    pub fn suggest(&self, term: &str, user_id: UserId, limit: usize) -> Vec<Song> {
+        let words = split_words(term);
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let tracks = self.tracks.read().unwrap();
+        let mut matches: Vec<_> = tracks
+            .iter()
+            .filter_map(|track| track.match_score(&words).map(|score| (score, track)))
+            .collect();
+        matches.sort_by(|(a_score, _), (b_score, _)| b_score.cmp(a_score));
+        matches
+            .into_iter()
+            .filter_map(|(_, track)| track.song(user_id))
+            .take(limit)
+            .collect()
+    }
+
+    /// Finds the track with a `file://` URL, e.g. from a saved playlist. URLs to files outside
+    /// the library's directories never match, even if they lead back into one with `..` or
+    /// symlinks.
+    pub async fn get(&self, url: &str, user_id: UserId) -> Option<Song> {
+        let path = url::Url::parse(url).ok()?.to_file_path().ok()?;
+        let path = tokio::fs::canonicalize(path).await.ok()?;
+        let is_in_directories = self.canonical_directories
+            .read()
+            .unwrap()
+            .iter()
+            .any(|directory| path.starts_with(directory));
+        if !is_in_directories {
+            return None;
+        }
+
+        let tracks = self.tracks.read().unwrap();
+        tracks
+            .iter()
+            .find(|track| track.path == path)
+            .and_then(|track| track.song(user_id))
+    }
+
+    /// Looks for audio files in the library's directories, reading tags from any that are new or
+    /// have changed, and saves the index. Returns how many tracks are in the library.
+    pub async fn scan(&self) -> Result<usize, Error> {
+        let directories = self.directories.clone();
+        let (canonical_directories, files) = tokio::task::spawn_blocking(move || {
+            // Tracks are stored with canonical paths so they can be compared to URLs in `get`.
+            let canonical_directories = canonicalize_directories(&directories);
+            let mut files = Vec::new();
+            for directory in &canonical_directories {
+                find_audio_files(directory, &mut files);
+            }
+            (canonical_directories, files)
+        }).await.map_err(Error::Runtime)?;
+        *self.canonical_directories.write().unwrap() = canonical_directories;
+
+        let mut known_tracks: HashMap<_, _> = self.tracks
+            .read()
+            .unwrap()
+            .iter()
+            .map(|track| ((track.path.clone(), track.modified_secs), track.clone()))
+            .collect();
+        let tracks: Vec<_> = stream::iter(files)
+            .map(|(path, modified_secs)| {
+                let known_track = known_tracks.remove(&(path.clone(), modified_secs));
+                async move {
+                    match known_track {
+                        Some(track) => Some(track),
+                        None => read_track(path, modified_secs).await,
+                    }
+                }
+            })
+            .buffered(PROBE_CONCURRENCY)
+            .filter_map(future::ready)
+            .collect()
+            .await;
+
+        let track_count = tracks.len();
+        let tracks_string = serde_json::to_string(&tracks).map_err(Error::Json)?;
+        *self.tracks.write().unwrap() = tracks;
+
+        if let Some(path) = &self.index_path {
+            // Write to a temporary file first so a crash mid-write can't corrupt the old index.
+            let temp_path = path.with_extension("tmp");
+            tokio::fs::write(&temp_path, &tracks_string).await.map_err(Error::Io)?;
+            tokio::fs::rename(&temp_path, path).await.map_err(Error::Io)?;
+        }
+
+        Ok(track_count)
+    }
+}
+
+/// Splits text into lower case words, ignoring punctuation.
+fn split_words(text: &str) -> Vec<String> {
+    text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Resolves symlinks and `..` in directory paths, leaving out directories that don't exist.
+fn canonicalize_directories(directories: &[PathBuf]) -> Vec<PathBuf> {
+    directories
+        .iter()
+        .filter_map(|directory| directory.canonicalize().ok())
+        .collect()
+}
+
+/// Adds every audio file under a directory to `files`, along with when it was modified.
+/// Directories that can't be read are skipped.
+fn find_audio_files(directory: &Path, files: &mut Vec<(PathBuf, u64)>) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // Symlinked directories aren't followed, since they could link back to a parent.
+        if entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false) {
+            find_audio_files(&path, files);
+            continue;
+        }
+
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => continue,
+        };
+
+        let is_audio_file = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| AUDIO_FILE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+            .unwrap_or(false);
+        if is_audio_file {
+            let modified_secs = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| modified.as_secs())
+                .unwrap_or_default();
+            files.push((path, modified_secs));
+        }
+    }
+}
+
+async fn read_track(path: PathBuf, modified_secs: u64) -> Option<LibraryTrack> {
+    let tags = probe(path.to_str()?).await.ok()?;
+    let title = tags.title.unwrap_or_else(|| {
+        path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+    });
+
+    Some(LibraryTrack {
+        path,
+        title,
+        artist: tags.artist,
+        album: tags.album,
+        duration: tags.duration,
+        modified_secs,
+    })
+}
@@ -0,0 +1,403 @@
+use crate::{open_source, Error, Song, SongList, SongMetadata, SourceInput, SourceOptions};
+use serde::{Serialize, Deserialize};
+use serenity::model::prelude::UserId;
+use songbird::input::Input;
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// File extensions that are played directly from HTTP URLs, instead of going through youtube-dl.
+pub(crate) const AUDIO_FILE_EXTENSIONS: &[&str] = &["aac", "flac", "m4a", "mp3", "oga", "ogg", "opus", "wav", "weba"];
+
+/// Selects a [`SourceResolver`]. Songs remember which kind loaded them so they're opened the same
+/// way, including after being saved and loaded again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolverKind {
+    /// Songs were always loaded with youtube-dl before resolvers could be picked.
+    #[default]
+    YoutubeDl,
+    YtDlp,
+    Http,
+    File,
+}
+
+impl ResolverKind {
+    pub fn resolver(self) -> &'static dyn SourceResolver {
+        static YOUTUBE_DL: YtdlResolver = YtdlResolver { kind: ResolverKind::YoutubeDl, command: "youtube-dl" };
+        static YT_DLP: YtdlResolver = YtdlResolver { kind: ResolverKind::YtDlp, command: "yt-dlp" };
+
+        match self {
+            ResolverKind::YoutubeDl => &YOUTUBE_DL,
+            ResolverKind::YtDlp => &YT_DLP,
+            ResolverKind::Http => &HttpResolver,
+            ResolverKind::File => &FileResolver,
+        }
+    }
+
+    /// Picks the first resolver in `resolvers` that accepts a term.
+    pub fn find(resolvers: &[ResolverKind], term: &str) -> Option<ResolverKind> {
+        resolvers.iter().copied().find(|kind| kind.resolver().accepts(term))
+    }
+}
+
+/// Turns search terms and URLs into songs, and opens those songs for playback.
+#[serenity::async_trait]
+pub trait SourceResolver: Send + Sync {
+    /// Whether the resolver should try to load a term. Terms it doesn't accept are left for the
+    /// next resolver.
+    fn accepts(&self, term: &str) -> bool;
+
+    async fn load(&self, term: &str, user_id: UserId) -> Result<Song, Error>;
+
+    /// Loads a term, expanding it into every song in a playlist if it's a playlist link.
+    async fn load_list(&self, term: &str, user_id: UserId) -> Result<SongList, Error> {
+        Ok(SongList::Single(self.load(term, user_id).await?))
+    }
+
+    /// Finds up to `count` songs matching a search term. Resolvers that can't search return no
+    /// results.
+    async fn search(&self, _term: &str, _count: usize, _user_id: UserId) -> Result<Vec<SearchResult>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Creates a playable source for a song this resolver loaded.
+    async fn open(&self, source_term: &str, options: SourceOptions, start: Duration) -> Result<Input, Error>;
+}
+
+/// A song found by [`SourceResolver::search`].
+pub struct SearchResult {
+    pub song: Song,
+
+    /// The channel or user that uploaded the song, if the resolver knows it.
+    pub channel: Option<String>,
+}
+
+/// Resolves anything youtube-dl or a fork of it supports, including searches.
+struct YtdlResolver {
+    kind: ResolverKind,
+    command: &'static str,
+}
+
+#[serenity::async_trait]
+impl SourceResolver for YtdlResolver {
+    fn accepts(&self, term: &str) -> bool {
+        // Anything that isn't a URL is a search. Other URLs, like files, are left for resolvers
+        // that check them.
+        match url::Url::parse(term) {
+            Ok(url) => url.scheme() == "http" || url.scheme() == "https",
+            Err(_) => true,
+        }
+    }
+
+    async fn load(&self, term: &str, user_id: UserId) -> Result<Song, Error> {
+        let query = match url::Url::parse(term).is_ok() {
+            true => term.to_string(),
+            false => format!("ytsearch1:{}", term),
+        };
+
+        let value = self.run(&["-j", "--no-playlist"], &query).await?;
+        Ok(self.song_from_json(&value, query, user_id))
+    }
+
+    async fn load_list(&self, term: &str, user_id: UserId) -> Result<SongList, Error> {
+        if url::Url::parse(term).is_err() {
+            return Ok(SongList::Single(self.load(term, user_id).await?));
+        }
+
+        // Flat playlists only list the songs in a playlist without resolving each one, which
+        // would take a long time for big playlists.
+        let value = self.run(&["-J", "--no-playlist", "--flat-playlist"], term).await?;
+        if value["_type"].as_str() != Some("playlist") {
+            return Ok(SongList::Single(self.song_from_json(&value, term.to_string(), user_id)));
+        }
+
+        let songs: Vec<_> = value["entries"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|entry| self.song_from_playlist_entry_json(entry, user_id))
+            .collect();
+        if songs.is_empty() {
+            return Err(Error::NoSongsFound);
+        }
+
+        Ok(SongList::Playlist {
+            title: value["title"].as_str().unwrap_or_default().to_string(),
+            url: value["webpage_url"].as_str().unwrap_or(term).to_string(),
+            songs,
+        })
+    }
+
+    async fn search(&self, term: &str, count: usize, user_id: UserId) -> Result<Vec<SearchResult>, Error> {
+        let query = format!("ytsearch{}:{}", count, term);
+        let value = self.run(&["-J", "--flat-playlist"], &query).await?;
+        let results = value["entries"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|entry| {
+                let channel = entry["channel"].as_str().or_else(|| entry["uploader"].as_str()).map(str::to_string);
+                self.song_from_playlist_entry_json(entry, user_id).map(|song| SearchResult { song, channel })
+            })
+            .collect();
+        Ok(results)
+    }
+
+    async fn open(&self, source_term: &str, options: SourceOptions, start: Duration) -> Result<Input, Error> {
+        let input = SourceInput::Ytdl {
+            command: self.command,
+            uri: source_term.to_string(),
+        };
+        open_source(input, options, start).await
+    }
+}
+
+impl YtdlResolver {
+    async fn run(&self, args: &[&str], query: &str) -> Result<serde_json::Value, Error> {
+        let output = tokio::process::Command::new(self.command)
+            .args(args)
+            .args(["--ignore-config", "--no-warnings"])
+            .arg(query)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .await
+            .map_err(Error::Io)?;
+
+        // Searches with no results succeed but print nothing.
+        let first_line = output.stdout
+            .split(|byte| *byte == b'\n')
+            .next()
+            .unwrap_or_default();
+        if !output.status.success() || first_line.is_empty() {
+            return Err(Error::NoSongsFound);
+        }
+
+        serde_json::from_slice(first_line).map_err(Error::Json)
+    }
+
+    fn song_from_json(&self, value: &serde_json::Value, query: String, user_id: UserId) -> Song {
+        let title = value["title"].as_str().unwrap_or_default().to_string();
+        let url = value["webpage_url"].as_str().unwrap_or_default().to_string();
+        let duration = value["duration"].as_f64().map(Duration::from_secs_f64);
+
+        // Prefer the resolved page URL so searches aren't repeated when the song is opened, but
+        // fall back to the original query if there isn't one.
+        let source_term = if url.is_empty() { query } else { url.clone() };
+
+        Song {
+            metadata: SongMetadata {
+                title,
+                url,
+                user_id,
+                duration,
+            },
+            source_term,
+            resolver: self.kind,
+        }
+    }
+
+    fn song_from_playlist_entry_json(&self, value: &serde_json::Value, user_id: UserId) -> Option<Song> {
+        // Some extractors only give the video ID for flat playlist entries.
+        let entry_url = value["url"].as_str()?;
+        let url = match (url::Url::parse(entry_url).is_ok(), value["ie_key"].as_str()) {
+            (true, _) => entry_url.to_string(),
+            (false, Some("Youtube")) => format!("https://www.youtube.com/watch?v={}", entry_url),
+            (false, _) => return None,
+        };
+
+        Some(Song {
+            metadata: SongMetadata {
+                title: value["title"].as_str().unwrap_or(&url).to_string(),
+                url: url.clone(),
+                user_id,
+                duration: value["duration"].as_f64().map(Duration::from_secs_f64),
+            },
+            source_term: url,
+            resolver: self.kind,
+        })
+    }
+}
+
+/// Plays links straight to audio files with ffmpeg.
+struct HttpResolver;
+
+#[serenity::async_trait]
+impl SourceResolver for HttpResolver {
+    fn accepts(&self, term: &str) -> bool {
+        let url = match url::Url::parse(term) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+            _ => return false,
+        };
+        let extension = url.path().rsplit('.').next().unwrap_or_default().to_lowercase();
+        url.path().contains('.') && AUDIO_FILE_EXTENSIONS.contains(&extension.as_str())
+    }
+
+    async fn load(&self, term: &str, user_id: UserId) -> Result<Song, Error> {
+        let tags = probe(term).await?;
+        let title = tags.title.unwrap_or_else(|| file_name(term).to_string());
+
+        Ok(Song {
+            metadata: SongMetadata {
+                title,
+                url: term.to_string(),
+                user_id,
+                duration: tags.duration,
+            },
+            source_term: term.to_string(),
+            resolver: ResolverKind::Http,
+        })
+    }
+
+    async fn open(&self, source_term: &str, options: SourceOptions, start: Duration) -> Result<Input, Error> {
+        check_public_url(source_term).await?;
+        open_source(SourceInput::Direct(source_term.to_string()), options, start).await
+    }
+}
+
+/// Plays `file://` URLs to tracks in the [`Library`](crate::Library). Files are only found through
+/// the library, which checks they're inside one of its directories, so this never loads terms
+/// itself.
+struct FileResolver;
+
+#[serenity::async_trait]
+impl SourceResolver for FileResolver {
+    fn accepts(&self, term: &str) -> bool {
+        matches!(url::Url::parse(term), Ok(url) if url.scheme() == "file")
+    }
+
+    async fn load(&self, _term: &str, _user_id: UserId) -> Result<Song, Error> {
+        Err(Error::NoSongsFound)
+    }
+
+    async fn open(&self, source_term: &str, options: SourceOptions, start: Duration) -> Result<Input, Error> {
+        let path = file_url_path(source_term).ok_or(Error::NoSongsFound)?;
+        open_source(SourceInput::Direct(path), options, start).await
+    }
+}
+
+fn file_url_path(url: &str) -> Option<String> {
+    let path = url::Url::parse(url).ok()?.to_file_path().ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Checks an http(s) URL doesn't point at the bot's own machine or network, so the bot can't be
+/// used to reach services that aren't public. Other terms are allowed through.
+pub(crate) async fn check_public_url(term: &str) -> Result<(), Error> {
+    let url = match url::Url::parse(term) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        _ => return Ok(()),
+    };
+
+    let addresses: Vec<IpAddr> = match url.host() {
+        Some(url::Host::Ipv4(address)) => vec![address.into()],
+        Some(url::Host::Ipv6(address)) => vec![address.into()],
+        Some(url::Host::Domain(domain)) => {
+            let port = url.port_or_known_default().unwrap_or_default();
+            tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|_| Error::NoSongsFound)?
+                .map(|address| address.ip())
+                .collect()
+        }
+        None => Vec::new(),
+    };
+    if addresses.is_empty() || !addresses.iter().all(is_public_address) {
+        return Err(Error::NoSongsFound);
+    }
+    Ok(())
+}
+
+/// Options for ffmpeg and ffprobe inputs. http(s) URLs can only be read from the host that
+/// [`check_public_url`] checked: redirects are refused, and only plain audio formats are read so
+/// playlists can't point somewhere else.
+pub(crate) fn input_options(input: &str) -> &'static [&'static str] {
+    match url::Url::parse(input) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => &[
+            "-protocol_whitelist",
+            "http,https,tcp,tls",
+            "-max_redirects",
+            "0",
+            "-format_whitelist",
+            "aac,flac,matroska,mov,mp3,ogg,wav",
+        ],
+        _ => &[],
+    }
+}
+
+fn is_public_address(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let octets = address.octets();
+            let is_shared = octets[0] == 100 && (octets[1] & 0xc0) == 64;
+            !(address.is_loopback() || address.is_private() || address.is_link_local()
+                || address.is_unspecified() || address.is_broadcast() || is_shared)
+        }
+        IpAddr::V6(address) => {
+            let segments = address.segments();
+            let is_unique_local = (segments[0] & 0xfe00) == 0xfc00;
+            let is_link_local = (segments[0] & 0xffc0) == 0xfe80;
+            let is_v4_mapped = segments[..5].iter().all(|segment| *segment == 0) && segments[5] == 0xffff;
+            if is_v4_mapped {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_address(&IpAddr::V4(std::net::Ipv4Addr::new(a, b, c, d)));
+            }
+            !(address.is_loopback() || address.is_unspecified() || is_unique_local || is_link_local)
+        }
+    }
+}
+
+/// Tags read from an audio file or URL by [`probe`].
+pub(crate) struct ProbedTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+/// Reads the tags and duration of an audio file or URL with ffprobe.
+pub(crate) async fn probe(input: &str) -> Result<ProbedTags, Error> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(["-v", "quiet", "-print_format", "json", "-show_format"])
+        .args(input_options(input))
+        .arg(input)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .map_err(Error::Io)?;
+    if !output.status.success() {
+        return Err(Error::NoSongsFound);
+    }
+
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(Error::Json)?;
+    let format = &value["format"];
+
+    // Tag names are upper case in some containers.
+    let tag = |name: &str| {
+        [name.to_string(), name.to_uppercase()]
+            .iter()
+            .find_map(|key| format["tags"][key].as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let duration = format["duration"]
+        .as_str()
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.)
+        .map(Duration::from_secs_f64);
+    Ok(ProbedTags {
+        title: tag("title"),
+        artist: tag("artist"),
+        album: tag("album"),
+        duration,
+    })
+}
+
+pub(crate) fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or(path)
+}
@@ -1,35 +1,107 @@
-use crate::Error;
+use crate::{check_public_url, Error, ResolverKind, SearchResult, SourceOptions};
 use serenity::model::prelude::UserId;
+use serde::{Serialize, Deserialize};
+use std::time::Duration;
 
+/// A song that has been resolved but not opened for playback. Songs are cheap to keep around, the
+/// playable source is only created by [`Song::open`] when the song starts playing.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Song {
     pub metadata: SongMetadata,
-    pub source: songbird::input::Input,
+    pub(crate) source_term: String,
+    #[serde(default)]
+    pub(crate) resolver: ResolverKind,
+}
+
+/// The result of loading a term that could be a playlist link.
+pub enum SongList {
+    Single(Song),
+    Playlist {
+        title: String,
+        url: String,
+        songs: Vec<Song>,
+    },
 }
 
 impl Song {
-    pub async fn load(term: &str, user_id: UserId) -> Result<Song, Error> {
-        let source = match url::Url::parse(term).is_ok() {
-            true => songbird::input::ytdl(term).await,
-            false => songbird::input::ytdl_search(term).await,
-        }.map_err(Error::SongbirdInput)?;
-
-        let title = source.metadata.title.clone().unwrap_or_default();
-        let url = source.metadata.source_url.clone().unwrap_or_default();
-
-        Ok(Song {
-            metadata: SongMetadata {
-                title,
-                url,
-                user_id,
-            },
-            source,
-        })
+    /// Loads a term with the first resolver in `resolvers` that accepts it and finds a song. If
+    /// every resolver fails, the last error is returned.
+    pub async fn load(resolvers: &[ResolverKind], term: &str, user_id: UserId) -> Result<Song, Error> {
+        check_public_url(term).await?;
+        let mut result = Err(Error::NoSongsFound);
+        for resolver in accepting_resolvers(resolvers, term) {
+            result = resolver.resolver().load(term, user_id).await;
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Loads a term like [`Song::load`], but expands playlist links into every song in the
+    /// playlist.
+    pub async fn load_list(resolvers: &[ResolverKind], term: &str, user_id: UserId) -> Result<SongList, Error> {
+        check_public_url(term).await?;
+        let mut result = Err(Error::NoSongsFound);
+        for resolver in accepting_resolvers(resolvers, term) {
+            result = resolver.resolver().load_list(term, user_id).await;
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Finds up to `count` songs matching a search term, using the first resolver in `resolvers`
+    /// that accepts the term and finds any.
+    pub async fn search(resolvers: &[ResolverKind], term: &str, count: usize, user_id: UserId) -> Result<Vec<SearchResult>, Error> {
+        for resolver in accepting_resolvers(resolvers, term) {
+            match resolver.resolver().search(term, count, user_id).await {
+                Ok(results) if results.is_empty() => {}
+                Err(Error::NoSongsFound) => {}
+                result => return result,
+            }
+        }
+        Err(Error::NoSongsFound)
+    }
+
+    /// Creates a song from metadata that was resolved earlier, without loading it again. It's
+    /// opened with the first resolver in `resolvers` that accepts its URL.
+    pub fn from_metadata(resolvers: &[ResolverKind], metadata: SongMetadata) -> Song {
+        Song {
+            source_term: metadata.url.clone(),
+            resolver: ResolverKind::find(resolvers, &metadata.url).unwrap_or_default(),
+            metadata,
+        }
     }
+
+    /// Spawns the processes needed to play the song. This should only be called right before the
+    /// song is played, since stream URLs can expire. Each call creates a new input, so a song can
+    /// be opened again to repeat it after the previous input has been consumed. The input starts
+    /// `start` into the song, and restarts its processes when it needs to seek so tracks playing
+    /// it can be seeked.
+    pub async fn open(&self, options: SourceOptions, start: Duration) -> Result<songbird::input::Input, Error> {
+        self.resolver.resolver().open(&self.source_term, options, start).await
+    }
+}
+
+fn accepting_resolvers<'a>(resolvers: &'a [ResolverKind], term: &'a str) -> impl Iterator<Item = ResolverKind> + 'a {
+    resolvers.iter().copied().filter(move |kind| kind.resolver().accepts(term))
 }
 
-#[derive(Clone)]
+impl mrvn_model::EntryDuration for Song {
+    fn duration(&self) -> Option<Duration> {
+        self.metadata.duration
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SongMetadata {
     pub title: String,
     pub url: String,
     pub user_id: UserId,
+
+    /// Not known for live streams.
+    #[serde(default)]
+    pub duration: Option<Duration>,
 }
@@ -0,0 +1,139 @@
+use crate::input_options;
+use mrvn_model::AudioFilter;
+use songbird::input::{Codec, Container, Input, Metadata, Restartable};
+use songbird::input::restartable::Restart;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Changes made to a song's audio before it's played.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceOptions {
+    /// Target loudness in LUFS. If set, songs are normalised towards it with ffmpeg's `loudnorm`
+    /// filter so quiet and loud uploads play at a similar level.
+    pub loudness_target: Option<f64>,
+
+    pub filters: Vec<AudioFilter>,
+}
+
+impl SourceOptions {
+    /// How much faster than the original the song plays. Positions in the original song are
+    /// divided by this to get positions in the played audio.
+    pub fn speed(&self) -> f64 {
+        self.filters
+            .iter()
+            .map(|filter| match filter {
+                AudioFilter::Speed | AudioFilter::Nightcore => 1.25,
+                AudioFilter::BassBoost | AudioFilter::EightD => 1.,
+            })
+            .product()
+    }
+
+    fn audio_filters(&self) -> Vec<String> {
+        let mut filters: Vec<_> = self.filters
+            .iter()
+            .map(|filter| match filter {
+                AudioFilter::BassBoost => "bass=g=10".to_string(),
+                AudioFilter::Speed => "atempo=1.25".to_string(),
+
+                // Speeding up by resampling also raises the pitch.
+                AudioFilter::Nightcore => "aresample=48000,asetrate=60000".to_string(),
+                AudioFilter::EightD => "apulsator=hz=0.125".to_string(),
+            })
+            .collect();
+        if let Some(loudness_target) = self.loudness_target {
+            filters.push(format!("loudnorm=I={}", loudness_target));
+        }
+        filters
+    }
+}
+
+/// Where ffmpeg reads a song from.
+pub(crate) enum SourceInput {
+    /// A page that a youtube-dl compatible command downloads and pipes to ffmpeg.
+    Ytdl {
+        command: &'static str,
+        uri: String,
+    },
+
+    /// A URL or file path that ffmpeg can open itself.
+    Direct(String),
+}
+
+/// Creates a source that plays `input` through ffmpeg, applying any filters in `options` and
+/// starting `start` into the song. The processes are started again to seek, the same as
+/// [`Restartable::ytdl`].
+pub(crate) async fn open_source(input: SourceInput, options: SourceOptions, start: Duration) -> Result<Input, crate::error::Error> {
+    let source = Restartable::new(FfmpegRestarter { input, options, start }, false)
+        .await
+        .map_err(crate::error::Error::SongbirdInput)?;
+    Ok(source.into())
+}
+
+struct FfmpegRestarter {
+    input: SourceInput,
+    options: SourceOptions,
+    start: Duration,
+}
+
+#[serenity::async_trait]
+impl Restart for FfmpegRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> songbird::input::error::Result<Input> {
+        // Seek times are positions in the played audio, which is sped up by some filters.
+        let position = self.start + time.unwrap_or_default().mul_f64(self.options.speed());
+
+        let mut ffmpeg_command = Command::new("ffmpeg");
+        if !position.is_zero() {
+            ffmpeg_command.args(["-ss", &format!("{:.3}", position.as_secs_f64())]);
+        }
+        let mut children = Vec::new();
+        match &self.input {
+            SourceInput::Ytdl { command, uri } => {
+                let mut youtube_dl = Command::new(command)
+                    .args([
+                        "-f",
+                        "webm[abr>0]/bestaudio/best",
+                        "-R",
+                        "infinite",
+                        "--no-playlist",
+                        "--ignore-config",
+                        "--no-warnings",
+                        uri,
+                        "-o",
+                        "-",
+                    ])
+                    .stdin(Stdio::null())
+                    .stderr(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .spawn()?;
+                let youtube_dl_stdout = youtube_dl.stdout.take().ok_or(songbird::input::error::Error::Stdout)?;
+                children.push(youtube_dl);
+                ffmpeg_command.args(["-i", "-"]).stdin(youtube_dl_stdout);
+            },
+            SourceInput::Direct(uri) => {
+                ffmpeg_command.args(input_options(uri)).args(["-i", uri]).stdin(Stdio::null());
+            },
+        }
+        let audio_filters = self.options.audio_filters();
+        if !audio_filters.is_empty() {
+            ffmpeg_command.args(["-af", &audio_filters.join(",")]);
+        }
+        let ffmpeg = ffmpeg_command
+            .args(["-f", "s16le", "-ac", "2", "-ar", "48000", "-acodec", "pcm_f32le", "-"])
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        children.push(ffmpeg);
+
+        Ok(Input::new(
+            true,
+            children.into(),
+            Codec::FloatPcm,
+            Container::Raw,
+            None,
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> songbird::input::error::Result<(Option<Metadata>, Codec, Container)> {
+        Ok((None, Codec::FloatPcm, Container::Raw))
+    }
+}
@@ -1,11 +1,11 @@
 use std::sync::Arc;
 use serenity::{prelude::*, model::prelude::*};
 use serenity::client::ClientBuilder;
-use crate::{Brain, Song, SongMetadata};
+use crate::{Brain, ResolverKind, Song, SongMetadata, SourceOptions};
 use dashmap::DashMap;
 use tokio::sync::MutexGuard;
 use std::ops::DerefMut;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub struct SpeakerKey;
 
@@ -43,7 +43,7 @@ impl Speaker {
         self.guilds
             .iter()
             .map(move |guild| {
-                let guild_id = guild.key().clone();
+                let guild_id = *guild.key();
                 let guild_speaker = guild.value().clone();
                 let current_call = self.songbird.get(guild_id);
                 GuildSpeakerHandle {
@@ -72,15 +72,65 @@ impl SpeakerInit for ClientBuilder<'_> {
     }
 }
 
+type EndedCallback = Box<dyn FnOnce(GuildSpeakerEndedHandle) + Send>;
+
+/// Holds what to do once the current song ends. This is shared with the track's end event so it
+/// can be moved to a new track when the song is restarted.
+type EndedSlot = Arc<Mutex<Option<(EndedCallback, GuildSpeakerEndedHandle)>>>;
+
+/// How often tracks check whether they've reached the point where they start crossfading.
+const CROSSFADE_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many times the volume changes over a crossfade.
+const CROSSFADE_STEPS: u32 = 20;
+
+/// How long before the current song ends, or starts crossfading, to open the next song's source.
+/// This is long enough for youtube-dl to start, without keeping a second source open for long.
+const PRELOAD_BEFORE_END: Duration = Duration::from_secs(30);
+
+/// How a song is played.
+#[derive(Debug, Clone, Default)]
+pub struct PlayOptions {
+    /// A multiplier where `1.0` is the original volume.
+    pub volume: f32,
+    pub source_options: SourceOptions,
+
+    /// If set, the next song starts this long before the current one ends, fading in while the
+    /// current song fades out. The ended handler is called when this starts, and can wait for the
+    /// song to actually end with [`GuildSpeakerEndedHandle::wait_for_end`].
+    pub crossfade: Option<Duration>,
+}
+
 struct GuildPlayingState {
-    metadata: SongMetadata,
+    song: Song,
+
+    /// The song expected to play after this one, which is preloaded near the end of this one.
+    next_song: Option<Song>,
     track: songbird::tracks::TrackHandle,
+    ended: EndedSlot,
     is_paused: bool,
+    options: PlayOptions,
+
+    /// The position in the song that the track started from.
+    start: Duration,
+}
+
+/// A source opened ahead of time for the song expected to play next, so it can start without
+/// waiting for youtube-dl.
+struct PreloadedSource {
+    source_term: String,
+    resolver: ResolverKind,
+    source_options: SourceOptions,
+
+    // Inputs can't be shared between threads, but the rest of the guild speaker is read from
+    // async code that can move between them.
+    source: std::sync::Mutex<songbird::input::Input>,
 }
 
 struct GuildSpeaker {
     last_ended_time: Option<Instant>,
     playing_state: Option<GuildPlayingState>,
+    preloaded: Option<PreloadedSource>,
 }
 
 impl GuildSpeaker {
@@ -88,6 +138,7 @@ impl GuildSpeaker {
         GuildSpeaker {
             last_ended_time: None,
             playing_state: None,
+            preloaded: None,
         }
     }
 }
@@ -151,13 +202,72 @@ impl<'handle> GuildSpeakerRef<'handle> {
     pub fn active_metadata(&self) -> Option<SongMetadata> {
         self.guild_speaker.playing_state
             .as_ref()
-            .map(|state| state.metadata.clone())
+            .map(|state| state.song.metadata.clone())
     }
 
-    pub async fn play<Ended: EndedHandler>(&mut self, channel_id: ChannelId, song: Song, ended_handler: Ended) -> Result<(), crate::error::Error> {
+    pub fn is_seekable(&self) -> bool {
+        match &self.guild_speaker.playing_state {
+            Some(state) => state.track.is_seekable(),
+            None => false,
+        }
+    }
+
+    /// Returns how far into the current song playback is, or `None` if nothing is playing.
+    pub async fn position(&self) -> Result<Option<Duration>, crate::error::Error> {
+        match &self.guild_speaker.playing_state {
+            Some(state) => match state.track.get_info().await {
+                Ok(track_state) => {
+                    let track_position = track_state.position.mul_f64(state.options.source_options.speed());
+                    Ok(Some(state.start + track_position))
+                }
+
+                // The track can end before the ended handler has cleared the playing state.
+                Err(songbird::error::TrackError::Finished) => Ok(None),
+                Err(why) => Err(crate::error::Error::SongbirdTrack(why)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Plays a song in a channel, replacing anything already playing, or crossfading from it if
+    /// it's still playing and `options` has a crossfade. If `next_song` is set its source is
+    /// opened shortly before this song ends, so it can start quickly if it's played next. Songs
+    /// without a known duration don't preload the next song.
+    pub async fn play<Ended: EndedHandler>(&mut self, channel_id: ChannelId, song: Song, options: PlayOptions, next_song: Option<Song>, ended_handler: Ended) -> Result<(), crate::error::Error> {
+        // Queued songs don't hold a source, so one is only opened now that it's being played,
+        // unless it was preloaded. A preloaded source for any other song is dropped, which stops
+        // its processes.
+        let source = match self.guild_speaker.preloaded.take() {
+            Some(preloaded) if preloaded.source_term == song.source_term && preloaded.resolver == song.resolver && preloaded.source_options == options.source_options => {
+                preloaded.source.into_inner().unwrap()
+            }
+            _ => match song.open(options.source_options.clone(), Duration::ZERO).await {
+                Ok(source) => source,
+                Err(why) => {
+                    // Don't leave the previous song's track behind, or the speaker would seem to
+                    // still be playing it.
+                    self.guild_speaker.playing_state = None;
+                    return Err(why);
+                }
+            },
+        };
+
+        // The previous song is still playing if this was called when it started crossfading.
+        let previous_state = self.guild_speaker.playing_state.take();
+        let fading_track = match (options.crossfade, previous_state) {
+            (Some(_), Some(previous_state)) => match previous_state.track.get_info().await {
+                Ok(track_state) if track_state.playing == songbird::tracks::PlayMode::Play => Some((previous_state.track, previous_state.options.volume)),
+                _ => None,
+            },
+            _ => None,
+        };
+
         let track_handle = match &mut self.current_call {
             Some(call) if call.current_channel() == Some(channel_id.into()) => {
-                call.play_only_source(song.source)
+                if fading_track.is_none() {
+                    call.stop();
+                }
+                call.play_source(source)
             },
             _ => {
                 // Ensure we don't deadlock by having a current_call lock
@@ -165,33 +275,96 @@ impl<'handle> GuildSpeakerRef<'handle> {
 
                 let (call_handle, join_result) = self.songbird.join(self.guild_id, channel_id).await;
                 if let Err(why) = join_result {
-                    self.guild_speaker.playing_state = None;
-                    return Err(crate::error::Error::SongbirdJoin(why));
+                    return Err(crate::error::Error::SongbirdJoin(Box::new(why)));
                 }
 
                 let mut call = call_handle.lock().await;
-                call.play_only_source(song.source)
+                call.play_only_source(source)
             }
         };
 
-        track_handle.add_event(songbird::Event::Track(songbird::TrackEvent::End), GuildSpeakerEndedEventHandler {
-            data: Mutex::new(Some((ended_handler, GuildSpeakerEndedHandle {
-                guild_id: self.guild_id,
-                songbird: self.songbird.clone(),
-                guild_speaker: self.guild_speaker_ref.clone(),
-            }))),
-        }).map_err(crate::error::Error::SongbirdTrack)?;
+        let ended_callback: EndedCallback = Box::new(move |ended_handle| ended_handler.on_ended(ended_handle));
+        let ended: EndedSlot = Arc::new(Mutex::new(Some((ended_callback, GuildSpeakerEndedHandle {
+            guild_id: self.guild_id,
+            songbird: self.songbird.clone(),
+            guild_speaker: self.guild_speaker_ref.clone(),
+            fading_track: None,
+        }))));
+        watch_track(&track_handle, ended.clone(), &options, song.metadata.duration)?;
+        if let Some(next_song) = &next_song {
+            preload_near_end(&track_handle, self.guild_speaker_ref.clone(), next_song.clone(), &options, song.metadata.duration)?;
+        }
+        if let (Some(crossfade), Some((fading_track, fading_volume))) = (options.crossfade, fading_track) {
+            track_handle.set_volume(0.).map_err(crate::error::Error::SongbirdTrack)?;
+            tokio::task::spawn(crossfade_tracks(fading_track, fading_volume, track_handle.clone(), options.volume, crossfade));
+        }
         self.guild_speaker.playing_state = Some(GuildPlayingState {
-            metadata: song.metadata,
+            song,
+            next_song,
             track: track_handle,
+            ended,
             is_paused: false,
+            options,
+            start: Duration::ZERO,
+        });
+
+        Ok(())
+    }
+
+    /// Starts the current song again from `position` with different source options, without
+    /// moving on to the next song.
+    async fn restart(&mut self, position: Duration, source_options: SourceOptions) -> Result<(), crate::error::Error> {
+        let (song, next_song, old_ended, is_paused, mut options) = match &self.guild_speaker.playing_state {
+            Some(state) => (state.song.clone(), state.next_song.clone(), state.ended.clone(), state.is_paused, state.options.clone()),
+            None => return Ok(()),
+        };
+        let call = match &mut self.current_call {
+            Some(call) => call,
+            None => return Ok(()),
+        };
+
+        let source = song.open(source_options.clone(), position).await?;
+
+        // Move the ended handler to the new track, so stopping the old track doesn't start the
+        // next song. If the handler is already gone the song finished while we were restarting it.
+        let ended_data = match old_ended.lock().await.take() {
+            Some(ended_data) => ended_data,
+            None => return Ok(()),
+        };
+        let ended: EndedSlot = Arc::new(Mutex::new(Some(ended_data)));
+
+        options.source_options = source_options;
+        let remaining_duration = song.metadata.duration.map(|duration| duration.saturating_sub(position));
+        let track_handle = call.play_only_source(source);
+        watch_track(&track_handle, ended.clone(), &options, remaining_duration)?;
+        if let Some(next_song) = &next_song {
+            preload_near_end(&track_handle, self.guild_speaker_ref.clone(), next_song.clone(), &options, remaining_duration)?;
+        }
+        if is_paused {
+            track_handle.pause().map_err(crate::error::Error::SongbirdTrack)?;
+        }
+        self.guild_speaker.playing_state = Some(GuildPlayingState {
+            song,
+            next_song,
+            track: track_handle,
+            ended,
+            is_paused,
+            options,
+            start: position,
         });
 
         Ok(())
     }
 
+    /// Applies new source options to the current song, restarting it from the current position.
+    pub async fn set_source_options(&mut self, source_options: SourceOptions) -> Result<(), crate::error::Error> {
+        let position = self.position().await?.unwrap_or_default();
+        self.restart(position, source_options).await
+    }
+
     pub fn unlock(&mut self) {
         self.guild_speaker.playing_state = None;
+        self.guild_speaker.preloaded = None;
         self.guild_speaker.last_ended_time = Some(Instant::now());
     }
 
@@ -210,6 +383,34 @@ impl<'handle> GuildSpeakerRef<'handle> {
         Ok(())
     }
 
+    pub async fn seek(&mut self, position: Duration) -> Result<(), crate::error::Error> {
+        let playing_state = match &self.guild_speaker.playing_state {
+            Some(playing_state) => playing_state,
+            None => return Ok(()),
+        };
+
+        // Tracks can only seek within the part of the song they were started from, and their
+        // positions are in the played audio rather than the original song.
+        match position.checked_sub(playing_state.start) {
+            Some(track_position) => {
+                let track_position = track_position.div_f64(playing_state.options.source_options.speed());
+                playing_state.track.seek_time(track_position).map_err(crate::error::Error::SongbirdTrack)
+            }
+            None => {
+                let source_options = playing_state.options.source_options.clone();
+                self.restart(position, source_options).await
+            }
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) -> Result<(), crate::error::Error> {
+        if let Some(playing_state) = &mut self.guild_speaker.playing_state {
+            playing_state.track.set_volume(volume).map_err(crate::error::Error::SongbirdTrack)?;
+            playing_state.options.volume = volume;
+        }
+        Ok(())
+    }
+
     pub fn unpause(&mut self) -> Result<(), crate::error::Error> {
         if let Some(playing_state) = &mut self.guild_speaker.playing_state {
             playing_state.track.play().map_err(crate::error::Error::SongbirdTrack)?;
@@ -219,26 +420,137 @@ impl<'handle> GuildSpeakerRef<'handle> {
     }
 
     pub async fn disconnect(&mut self) -> Result<(), crate::error::Error> {
+        self.guild_speaker.preloaded = None;
         if let Some(call) = &mut self.current_call {
-            call.leave().await.map_err(crate::error::Error::SongbirdJoin)?;
+            call.leave().await.map_err(|why| crate::error::Error::SongbirdJoin(Box::new(why)))?;
         }
         Ok(())
     }
 }
 
-struct GuildSpeakerEndedEventHandler<Ended: EndedHandler> {
-    data: Mutex<Option<(Ended, GuildSpeakerEndedHandle)>>,
+/// Sets up a new track to call the ended handler when it ends, or when it starts crossfading if
+/// `remaining_duration` is how long is left of the song.
+fn watch_track(track_handle: &songbird::tracks::TrackHandle, ended: EndedSlot, options: &PlayOptions, remaining_duration: Option<Duration>) -> Result<(), crate::error::Error> {
+    track_handle.set_volume(options.volume).map_err(crate::error::Error::SongbirdTrack)?;
+    track_handle.add_event(songbird::Event::Track(songbird::TrackEvent::End), GuildSpeakerEndedEventHandler {
+        data: ended.clone(),
+        crossfade_start: None,
+    }).map_err(crate::error::Error::SongbirdTrack)?;
+
+    // Songs without a known duration can't crossfade, since we don't know when to start.
+    if let (Some(crossfade), Some(remaining_duration)) = (options.crossfade, remaining_duration) {
+        let track_duration = remaining_duration.div_f64(options.source_options.speed());
+        if let Some(crossfade_start) = track_duration.checked_sub(crossfade) {
+            track_handle.add_event(songbird::Event::Periodic(CROSSFADE_CHECK_INTERVAL, None), GuildSpeakerEndedEventHandler {
+                data: ended,
+                crossfade_start: Some(crossfade_start),
+            }).map_err(crate::error::Error::SongbirdTrack)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens the next song's source shortly before a track ends, if `remaining_duration` is how long
+/// is left of the song.
+fn preload_near_end(track_handle: &songbird::tracks::TrackHandle, guild_speaker: Arc<Mutex<GuildSpeaker>>, next_song: Song, options: &PlayOptions, remaining_duration: Option<Duration>) -> Result<(), crate::error::Error> {
+    let remaining_duration = match remaining_duration {
+        Some(remaining_duration) => remaining_duration,
+        None => return Ok(()),
+    };
+    let track_duration = remaining_duration.div_f64(options.source_options.speed());
+    let preload_start = track_duration.saturating_sub(PRELOAD_BEFORE_END + options.crossfade.unwrap_or_default());
+    track_handle.add_event(songbird::Event::Delayed(preload_start), GuildSpeakerPreloadEventHandler {
+        guild_speaker,
+        track_id: track_handle.uuid(),
+        next_song,
+        source_options: options.source_options.clone(),
+    }).map_err(crate::error::Error::SongbirdTrack)
+}
+
+/// Fades one track out and another in, then stops the faded out track.
+async fn crossfade_tracks(fading_track: songbird::tracks::TrackHandle, fading_volume: f32, track: songbird::tracks::TrackHandle, volume: f32, crossfade: Duration) {
+    for step in 1..=CROSSFADE_STEPS {
+        tokio::time::sleep(crossfade / CROSSFADE_STEPS).await;
+        let progress = step as f32 / CROSSFADE_STEPS as f32;
+
+        // Either track may have been stopped or skipped in the meantime.
+        let _ = fading_track.set_volume(fading_volume * (1. - progress));
+        let _ = track.set_volume(volume * progress);
+    }
+    let _ = fading_track.stop();
+}
+
+struct GuildSpeakerEndedEventHandler {
+    data: EndedSlot,
+
+    /// The track position to call the ended handler at, instead of when the track ends.
+    crossfade_start: Option<Duration>,
 }
 
 #[serenity::async_trait]
-impl<Ended: EndedHandler> songbird::events::EventHandler for GuildSpeakerEndedEventHandler<Ended> {
-    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+impl songbird::events::EventHandler for GuildSpeakerEndedEventHandler {
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        if let (Some(crossfade_start), songbird::EventContext::Track(tracks)) = (self.crossfade_start, ctx) {
+            if tracks.iter().all(|(track_state, _)| track_state.position < crossfade_start) {
+                return None;
+            }
+        }
+
         let mut data_ref = self.data.lock().await;
-        let data = std::mem::replace(data_ref.deref_mut(), None);
-        if let Some((ended_handler, handle)) = data {
-            ended_handler.on_ended(handle);
+        let data = data_ref.deref_mut().take();
+        if let Some((ended_callback, mut handle)) = data {
+            if let (Some(_), songbird::EventContext::Track(tracks)) = (self.crossfade_start, ctx) {
+                handle.fading_track = tracks.first().map(|(_, track_handle)| (*track_handle).clone());
+            }
+            ended_callback(handle);
         }
 
+        // Stop checking once the crossfade has started.
+        self.crossfade_start.map(|_| songbird::Event::Cancel)
+    }
+}
+
+struct GuildSpeakerPreloadEventHandler {
+    guild_speaker: Arc<Mutex<GuildSpeaker>>,
+
+    /// The track that was playing when this was set up. The preloaded source is thrown away if a
+    /// different one is playing by the time it's opened.
+    track_id: uuid::Uuid,
+    next_song: Song,
+    source_options: SourceOptions,
+}
+
+#[serenity::async_trait]
+impl songbird::events::EventHandler for GuildSpeakerPreloadEventHandler {
+    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        // Opening a source can take a while, so it's done separately to avoid holding up the
+        // track's other events.
+        let guild_speaker = self.guild_speaker.clone();
+        let track_id = self.track_id;
+        let next_song = self.next_song.clone();
+        let source_options = self.source_options.clone();
+        tokio::task::spawn(async move {
+            // A song that fails to preload is opened again when it plays, which reports the error.
+            let source = match next_song.open(source_options.clone(), Duration::ZERO).await {
+                Ok(source) => source,
+                Err(_) => return,
+            };
+
+            let mut guild_speaker = guild_speaker.lock().await;
+            let is_same_track = guild_speaker.playing_state
+                .as_ref()
+                .map(|state| state.track.uuid() == track_id)
+                .unwrap_or(false);
+            if is_same_track {
+                guild_speaker.preloaded = Some(PreloadedSource {
+                    source_term: next_song.source_term,
+                    resolver: next_song.resolver,
+                    source_options,
+                    source: std::sync::Mutex::new(source),
+                });
+            }
+        });
         None
     }
 }
@@ -251,6 +563,10 @@ pub struct GuildSpeakerEndedHandle {
     guild_id: GuildId,
     songbird: Arc<songbird::Songbird>,
     guild_speaker: Arc<Mutex<GuildSpeaker>>,
+
+    /// The song's track, if the handle was given when it started crossfading rather than when it
+    /// ended. It's still playing while it fades out.
+    fading_track: Option<songbird::tracks::TrackHandle>,
 }
 
 impl GuildSpeakerEndedHandle {
@@ -263,16 +579,63 @@ impl GuildSpeakerEndedHandle {
         }
     }
 
-    pub async fn play<Ended: EndedHandler>(self, channel_id: ChannelId, song: Song, ended_handler: Ended) -> Result<(), crate::error::Error> {
+    /// Plays the next song. The handle is kept if this fails, so a different song can be tried.
+    pub async fn play<Ended: EndedHandler>(&self, channel_id: ChannelId, song: Song, options: PlayOptions, next_song: Option<Song>, ended_handler: Ended) -> Result<(), crate::error::Error> {
         let handle = self.get_handle();
         let mut guild_speaker_ref = handle.lock().await;
-        guild_speaker_ref.play(channel_id, song, ended_handler).await
+        guild_speaker_ref.play(channel_id, song, options, next_song, ended_handler).await
+    }
+
+    /// Whether the song is still fading out, because the handle was given when its crossfade
+    /// started.
+    pub fn is_fading(&self) -> bool {
+        self.fading_track.is_some()
+    }
+
+    /// Calls `ended_handler` again once a song that's fading out has actually ended, for when
+    /// there's nothing to crossfade into. The song keeps playing until then, so it can still be
+    /// paused or skipped. Gives the handle back if the song isn't fading out or has already ended.
+    pub async fn wait_for_end<Ended: EndedHandler>(mut self, ended_handler: Ended) -> Result<(), Self> {
+        let fading_track = match self.fading_track.take() {
+            Some(fading_track) => fading_track,
+            None => return Err(self),
+        };
+
+        let mut guild_speaker = self.guild_speaker.lock().await;
+        let playing_state = match &mut guild_speaker.playing_state {
+            Some(playing_state) if playing_state.track.uuid() == fading_track.uuid() => playing_state,
+            _ => {
+                drop(guild_speaker);
+                return Err(self);
+            }
+        };
+
+        // The track's own end event was used up when the crossfade started, so a new one is added.
+        // It's also kept in the playing state, so it moves to the new track if the song restarts.
+        let ended_callback: EndedCallback = Box::new(move |ended_handle| ended_handler.on_ended(ended_handle));
+        let ended: EndedSlot = Arc::new(Mutex::new(Some((ended_callback, GuildSpeakerEndedHandle {
+            guild_id: self.guild_id,
+            songbird: self.songbird.clone(),
+            guild_speaker: self.guild_speaker.clone(),
+            fading_track: None,
+        }))));
+        let add_result = fading_track.add_event(songbird::Event::Track(songbird::TrackEvent::End), GuildSpeakerEndedEventHandler {
+            data: ended.clone(),
+            crossfade_start: None,
+        });
+        if add_result.is_err() {
+            drop(guild_speaker);
+            return Err(self);
+        }
+        playing_state.ended = ended;
+        Ok(())
     }
 
     pub async fn stop(self) {
         let handle = self.get_handle();
         let mut guild_speaker_ref = handle.lock().await;
         guild_speaker_ref.guild_speaker.playing_state = None;
+        guild_speaker_ref.guild_speaker.preloaded = None;
         guild_speaker_ref.guild_speaker.last_ended_time = Some(Instant::now());
     }
 }
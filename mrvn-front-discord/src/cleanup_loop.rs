@@ -5,8 +5,10 @@ use crate::frontend::Frontend;
 use futures::prelude::*;
 use mrvn_back_ytdl::GuildSpeakerHandle;
 
-async fn check_cleanup_for_speaker(guild_speaker_handle: GuildSpeakerHandle, cache: Arc<serenity::cache::Cache>, config: Arc<Config>) {
+async fn check_cleanup_for_speaker(guild_speaker_handle: GuildSpeakerHandle, cache: Arc<serenity::cache::Cache>, frontend: Arc<Frontend>) {
     let mut guild_speaker = guild_speaker_handle.lock().await;
+    let config: &Config = &frontend.config;
+    let guild_config = frontend.guild_configs.get(guild_speaker.guild_id());
 
     // Ignore the speaker if it's currently active or not even connected
     if guild_speaker.is_active() {
@@ -26,11 +28,11 @@ async fn check_cleanup_for_speaker(guild_speaker_handle: GuildSpeakerHandle, cac
     };
 
     // Ignore the speaker if not enough time has passed since last playback
-    if last_ended_time.elapsed().as_secs() < config.disconnect_min_inactive_secs {
+    if last_ended_time.elapsed().as_secs() < guild_config.disconnect_min_inactive_secs(config) {
         return;
     }
 
-    if config.only_disconnect_when_alone {
+    if guild_config.only_disconnect_when_alone(config) {
         if let Some(guild) = cache.clone().guild(guild_speaker.guild_id()).await {
             if let Some(channel) = guild.channels.get(&channel_id) {
                 if let Ok(members) = channel.members(&cache).await {
@@ -57,7 +59,7 @@ async fn check_cleanup(frontend: Arc<Frontend>, cache: Arc<serenity::cache::Cach
     let futures = frontend.backend_brain.speakers
         .iter()
         .flat_map(|speaker| speaker.iter())
-        .map(|guild_speaker_handle| check_cleanup_for_speaker(guild_speaker_handle, cache.clone(), frontend.config.clone()));
+        .map(|guild_speaker_handle| check_cleanup_for_speaker(guild_speaker_handle, cache.clone(), frontend.clone()));
 
     future::join_all(futures).await;
     log::trace!("Finished disconnecting inactive speakers, {} secs", work_start_time.elapsed().as_secs_f64());
@@ -21,8 +21,11 @@ impl EventHandler for CommandHandler {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(command) = interaction {
-            self.frontend.handle_command(&ctx, &command).await;
+        match interaction {
+            Interaction::ApplicationCommand(command) => self.frontend.handle_command(&ctx, &command).await,
+            Interaction::MessageComponent(component) => self.frontend.handle_component(&ctx, &component).await,
+            Interaction::Autocomplete(autocomplete) => self.frontend.handle_autocomplete(&ctx, &autocomplete).await,
+            _ => {}
         }
     }
 }
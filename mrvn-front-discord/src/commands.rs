@@ -19,14 +19,255 @@ fn play_command(command: &mut serenity::builder::CreateApplicationCommand) -> &m
                 .description("A search term or song link.")
                 .kind(application_command::ApplicationCommandOptionType::String)
                 .required(false)
+                .set_autocomplete(true)
         })
 }
 
-//fn queue_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
-//    command
-//        .name("queue")
-//        .description("View and manage your queue.")
-//}
+fn search_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("search")
+        .description("Search for a song and pick which result to add to your queue.")
+        .create_option(|option| {
+            option
+                .name("term")
+                .description("A search term.")
+                .kind(application_command::ApplicationCommandOptionType::String)
+                .required(true)
+        })
+}
+
+fn queue_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("queue")
+        .description("View and manage your queue.")
+        .create_option(|option| {
+            option
+                .name("show")
+                .description("View your queue and what's playing next.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("page")
+                        .description("The page of the queue to show.")
+                        .kind(application_command::ApplicationCommandOptionType::Integer)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Remove a song from your queue.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("position")
+                        .description("The position of the song in your queue.")
+                        .kind(application_command::ApplicationCommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("move")
+                .description("Move a song to a different position in your queue.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("from")
+                        .description("The current position of the song in your queue.")
+                        .kind(application_command::ApplicationCommandOptionType::Integer)
+                        .required(true)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("to")
+                        .description("The new position of the song in your queue.")
+                        .kind(application_command::ApplicationCommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("clear")
+                .description("Remove all songs from your queue.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+fn queue_mode_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("queuemode")
+        .description("Choose whether your voice channel has one shared queue.")
+        .create_option(|option| {
+            option
+                .name("mode")
+                .description("The queue mode for your voice channel, leave empty to use the server default.")
+                .kind(application_command::ApplicationCommandOptionType::String)
+                .add_string_choice("Per-user queues", "per_user")
+                .add_string_choice("Shared queue", "shared")
+                .required(false)
+        })
+}
+
+fn loop_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("loop")
+        .description("Repeat the current song or queue in your voice channel.")
+        .create_option(|option| {
+            option
+                .name("mode")
+                .description("What to repeat when a song finishes.")
+                .kind(application_command::ApplicationCommandOptionType::String)
+                .add_string_choice("Off", "off")
+                .add_string_choice("Current song", "song")
+                .add_string_choice("Queue", "queue")
+                .required(true)
+        })
+}
+
+fn config_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("config")
+        .description("View or change settings for this server.")
+        .create_option(|option| {
+            option
+                .name("get")
+                .description("Show the settings for this server.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("set")
+                .description("Change a setting for this server.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    let option = option
+                        .name("key")
+                        .description("The setting to change.")
+                        .kind(application_command::ApplicationCommandOptionType::String)
+                        .required(true);
+                    for key in crate::guild_config::GUILD_CONFIG_KEYS {
+                        option.add_string_choice(key, key);
+                    }
+                    option
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("value")
+                        .description("The new value, leave empty to use the default.")
+                        .kind(application_command::ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+}
+
+fn playlist_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("playlist")
+        .description("Save your queue as a playlist, or queue a saved playlist.")
+        .create_option(|option| {
+            option
+                .name("save")
+                .description("Save the songs in your queue as a playlist.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("name")
+                        .description("The name of the playlist. An existing playlist with this name is replaced.")
+                        .kind(application_command::ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("load")
+                .description("Add the songs in a saved playlist to your queue.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("name")
+                        .description("The name of the playlist.")
+                        .kind(application_command::ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("Show your saved playlists.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("delete")
+                .description("Delete a saved playlist.")
+                .kind(application_command::ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("name")
+                        .description("The name of the playlist.")
+                        .kind(application_command::ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+}
+
+fn volume_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("volume")
+        .description("Change the volume of songs in your voice channel.")
+        .create_option(|option| {
+            option
+                .name("volume")
+                .description("The volume as a percentage, from 0 to 200.")
+                .kind(application_command::ApplicationCommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+fn filter_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("filter")
+        .description("Turn an audio effect on or off in your voice channel.")
+        .create_option(|option| {
+            option
+                .name("name")
+                .description("The effect to turn on or off.")
+                .kind(application_command::ApplicationCommandOptionType::String)
+                .required(true)
+                .add_string_choice("Bass boost", "bassboost")
+                .add_string_choice("Speed", "speed")
+                .add_string_choice("Nightcore", "nightcore")
+                .add_string_choice("8D", "8d")
+                .add_string_choice("Off", "off")
+        })
+}
+
+fn history_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("history")
+        .description("Show songs that have recently played.")
+        .create_option(|option| {
+            option
+                .name("page")
+                .description("The page of the history to show.")
+                .kind(application_command::ApplicationCommandOptionType::Integer)
+                .required(false)
+        })
+}
+
+fn requeue_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("requeue")
+        .description("Add a song from the history to your queue.")
+        .create_option(|option| {
+            option
+                .name("position")
+                .description("The position of the song in the history.")
+                .kind(application_command::ApplicationCommandOptionType::Integer)
+                .required(true)
+        })
+}
 
 fn replace_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
     command
@@ -38,6 +279,7 @@ fn replace_command(command: &mut serenity::builder::CreateApplicationCommand) ->
                 .description("A search term or song link.")
                 .kind(application_command::ApplicationCommandOptionType::String)
                 .required(true)
+                .set_autocomplete(true)
         })
 }
 
@@ -59,6 +301,37 @@ fn stop_command(command: &mut serenity::builder::CreateApplicationCommand) -> &m
         .description("Vote to skip the current song and stop playback.")
 }
 
+fn seek_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("seek")
+        .description("Jump to a time in the current song, or vote to if it isn't yours.")
+        .create_option(|option| {
+            option
+                .name("timestamp")
+                .description("The time to jump to, e.g. 1:23.")
+                .kind(application_command::ApplicationCommandOptionType::String)
+                .required(true)
+        })
+}
+
+fn now_playing_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("nowplaying")
+        .description("Show the current song and how far through it is.")
+}
+
+fn force_skip_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("forceskip")
+        .description("Skip the current song without a vote. Only available to DJs.")
+}
+
+fn force_stop_command(command: &mut serenity::builder::CreateApplicationCommand) -> &mut serenity::builder::CreateApplicationCommand {
+    command
+        .name("forcestop")
+        .description("Skip the current song and stop playback without a vote. Only available to DJs.")
+}
+
 pub async fn register_commands(http: impl AsRef<serenity::http::Http>, guild_id: Option<GuildId>) -> serenity::Result<()> {
     let http_ref = http.as_ref();
     match guild_id {
@@ -67,11 +340,24 @@ pub async fn register_commands(http: impl AsRef<serenity::http::Http>, guild_id:
             log::trace!("Registering guild application commands");
             futures::try_join!(
                 guild_id.create_application_command(http_ref, play_command),
-                // guild_id.create_application_command(http_ref, queue_command),
+                guild_id.create_application_command(http_ref, search_command),
+                guild_id.create_application_command(http_ref, queue_command),
+                guild_id.create_application_command(http_ref, queue_mode_command),
+                guild_id.create_application_command(http_ref, loop_command),
+                guild_id.create_application_command(http_ref, config_command),
+                guild_id.create_application_command(http_ref, playlist_command),
+                guild_id.create_application_command(http_ref, volume_command),
+                guild_id.create_application_command(http_ref, filter_command),
+                guild_id.create_application_command(http_ref, history_command),
+                guild_id.create_application_command(http_ref, requeue_command),
                 guild_id.create_application_command(http_ref, replace_command),
                 guild_id.create_application_command(http_ref, pause_command),
                 guild_id.create_application_command(http_ref, skip_command),
                 guild_id.create_application_command(http_ref, stop_command),
+                guild_id.create_application_command(http_ref, seek_command),
+                guild_id.create_application_command(http_ref, now_playing_command),
+                guild_id.create_application_command(http_ref, force_skip_command),
+                guild_id.create_application_command(http_ref, force_stop_command),
             )?;
         },
         None => {
@@ -79,10 +365,24 @@ pub async fn register_commands(http: impl AsRef<serenity::http::Http>, guild_id:
             application_command::ApplicationCommand::set_global_application_commands(http_ref, |commands| {
                 commands
                     .create_application_command(play_command)
+                    .create_application_command(search_command)
+                    .create_application_command(queue_command)
+                    .create_application_command(queue_mode_command)
+                    .create_application_command(loop_command)
+                    .create_application_command(config_command)
+                    .create_application_command(playlist_command)
+                    .create_application_command(volume_command)
+                    .create_application_command(filter_command)
+                    .create_application_command(history_command)
+                    .create_application_command(requeue_command)
                     .create_application_command(replace_command)
                     .create_application_command(pause_command)
                     .create_application_command(skip_command)
                     .create_application_command(stop_command)
+                    .create_application_command(seek_command)
+                    .create_application_command(now_playing_command)
+                    .create_application_command(force_skip_command)
+                    .create_application_command(force_stop_command)
             }).await?;
         }
     };
@@ -1,5 +1,6 @@
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Duration;
 use serde::de::Error;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -19,19 +20,61 @@ pub struct VoiceBot {
 pub struct Config {
     #[serde(deserialize_with = "from_hex")]
     pub embed_color: u32,
-    pub skip_votes_required: usize,
-    pub stop_votes_required: usize,
+    pub skip_votes_required: mrvn_model::VoteThreshold,
+    pub stop_votes_required: mrvn_model::VoteThreshold,
+    #[serde(default)]
+    pub dj_role_ids: HashMap<u64, Vec<u64>>,
+    #[serde(default)]
+    pub scheduling_policy: mrvn_model::SchedulingPolicyKind,
+    #[serde(default)]
+    pub queue_mode: mrvn_model::QueueMode,
+    #[serde(default = "default_history_length")]
+    pub history_length: usize,
+    pub max_queue_length: Option<usize>,
+    pub max_queue_duration_secs: Option<u64>,
+    pub max_song_duration_secs: Option<u64>,
+    #[serde(default)]
+    pub volume_requires_dj: bool,
+    #[serde(default, deserialize_with = "loudness_target_lufs")]
+    pub loudness_target_lufs: Option<f64>,
+    pub crossfade_secs: Option<f64>,
+    #[serde(default = "default_source_resolvers")]
+    pub source_resolvers: Vec<mrvn_back_ytdl::ResolverKind>,
 
     pub disconnect_min_inactive_secs: u64,
     pub disconnect_check_interval_secs: u64,
     pub only_disconnect_when_alone: bool,
 
+    pub state_file_path: Option<String>,
+    pub guild_config_path: Option<String>,
+    pub playlist_file_path: Option<String>,
+    #[serde(default)]
+    pub library_directories: Vec<String>,
+    pub library_index_path: Option<String>,
+    #[serde(default = "default_library_scan_interval_secs", deserialize_with = "interval_secs")]
+    pub library_scan_interval_secs: u64,
+    #[serde(default = "default_state_save_interval_secs", deserialize_with = "interval_secs")]
+    pub state_save_interval_secs: u64,
+
     pub command_bot: CommandBot,
     pub voice_bots: Vec<VoiceBot>,
     pub messages: HashMap<String, String>,
 }
 
 impl Config {
+    pub fn model_config(&self) -> mrvn_model::AppModelConfig {
+        mrvn_model::AppModelConfig {
+            skip_votes_required: self.skip_votes_required,
+            stop_votes_required: self.stop_votes_required,
+            scheduling_policy: self.scheduling_policy,
+            queue_mode: self.queue_mode,
+            history_length: self.history_length,
+            max_user_entries: self.max_queue_length,
+            max_user_duration: self.max_queue_duration_secs.map(Duration::from_secs),
+            max_entry_duration: self.max_song_duration_secs.map(Duration::from_secs),
+        }
+    }
+
     pub fn get_raw_message<'s>(&'s self, message_key: &'s str) -> &'s str {
         match self.messages.get(message_key) {
             Some(template) => template,
@@ -60,7 +103,41 @@ impl Config {
     }
 }
 
+fn default_history_length() -> usize {
+    50
+}
+
+fn default_source_resolvers() -> Vec<mrvn_back_ytdl::ResolverKind> {
+    vec![mrvn_back_ytdl::ResolverKind::YoutubeDl]
+}
+
+fn default_library_scan_interval_secs() -> u64 {
+    3600
+}
+
+fn default_state_save_interval_secs() -> u64 {
+    30
+}
+
 fn from_hex<'de, D>(deserializer: D) -> Result<u32, D::Error> where D: serde::Deserializer<'de> {
     let s: String = Deserialize::deserialize(deserializer)?;
     u32::from_str_radix(&s, 16).map_err(D::Error::custom)
 }
+
+/// Reads how many seconds to wait between runs of a loop, which can't be zero.
+fn interval_secs<'de, D>(deserializer: D) -> Result<u64, D::Error> where D: serde::Deserializer<'de> {
+    let secs: u64 = Deserialize::deserialize(deserializer)?;
+    match secs {
+        0 => Err(D::Error::custom("interval must be at least 1 second")),
+        secs => Ok(secs),
+    }
+}
+
+/// Reads a loudness target, which has to be in the range ffmpeg's `loudnorm` filter accepts.
+fn loudness_target_lufs<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error> where D: serde::Deserializer<'de> {
+    let maybe_lufs: Option<f64> = Deserialize::deserialize(deserializer)?;
+    match maybe_lufs {
+        Some(lufs) if !(-70. ..=-5.).contains(&lufs) => Err(D::Error::custom("loudness target must be between -70 and -5 LUFS")),
+        maybe_lufs => Ok(maybe_lufs),
+    }
+}
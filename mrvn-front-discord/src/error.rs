@@ -4,6 +4,9 @@ use serenity::model::prelude::*;
 pub enum Error {
     Serenity(serenity::Error),
     Backend(mrvn_back_ytdl::Error),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedStateVersion(u32),
 
     UnknownCommand(String),
     NoGuild,
@@ -16,6 +19,9 @@ impl std::fmt::Display for Error {
         match self {
             Error::Serenity(err) => err.fmt(f),
             Error::Backend(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+            Error::Json(err) => err.fmt(f),
+            Error::UnsupportedStateVersion(version) => write!(f, "Unsupported state file version {}", version),
             Error::UnknownCommand(command) => write!(f, "Received unknown command {}", command),
             Error::NoGuild => write!(f, "Command was not invoked from a guild"),
             Error::UnknownGuild(guild_id) => write!(f, "Unknown guild {}", guild_id),
@@ -1,70 +1,211 @@
-use mrvn_back_ytdl::{Brain, Song, EndedHandler, GuildSpeakerEndedHandle};
-use mrvn_model::{AppModel, GuildModel, NextEntry, VoteStatus, ReplaceStatus, VoteType};
+use mrvn_back_ytdl::{Brain, Library, Song, SongList, SongMetadata, SourceOptions, PlayOptions, EndedHandler, GuildSpeakerEndedHandle};
+use mrvn_model::{AppModel, AppModelDelegate, AudioFilter, GuildModel, NextEntry, VoteStatus, ReplaceStatus, VoteType, QueueMode, RepeatMode, QueueLimit};
 use std::sync::Arc;
-use serenity::{prelude::*, model::prelude::{UserId, GuildId, interactions, application_command}};
+use serenity::{prelude::*, model::prelude::{UserId, GuildId, Member, interactions, application_command}};
 use crate::config::Config;
+use crate::guild_config::GuildConfigStore;
+use crate::playlist_store::{PlaylistStore, SavedSong};
+use crate::search_cache::{CachedResult, SearchCache};
 use std::ops::DerefMut;
-use crate::message::{send_messages, Message, ResponseMessage, ActionMessage, SendMessageDestination};
+use crate::message::{send_messages, InteractionRef, Message, ResponseMessage, ActionMessage, SendMessageDestination, QueuedSong, PlayedSong, ConfigEntry, SearchResult, SEARCH_SELECT_MENU_ID, PLAYING_PAUSE_BUTTON_ID, PLAYING_SKIP_BUTTON_ID, PLAYING_STOP_BUTTON_ID, PLAYING_QUEUE_BUTTON_ID};
 use crate::model_delegate::ModelDelegate;
 use serenity::model::id::ChannelId;
 use std::time::Duration;
 use futures::prelude::*;
 
+/// The loudest volume `/volume` accepts, as a percentage of the original volume.
+const MAX_VOLUME: u32 = 200;
+
 const SEND_WORKING_TIMEOUT_MS: u64 = 50;
+const SEARCH_RESULT_COUNT: usize = 5;
+
+/// Discord's limit on the length of select menu option values.
+const MAX_SELECT_VALUE_LENGTH: usize = 100;
+const QUEUE_PAGE_SIZE: usize = 8;
+
+/// Discord's limit on how many choices an autocomplete response can have.
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// Discord's limit on the length of autocomplete choice names and values.
+const MAX_AUTOCOMPLETE_CHOICE_LENGTH: usize = 100;
 
 enum HandleCommandError {
     CreateError(crate::error::Error),
     EditError(crate::error::Error),
 }
 
+/// A command DJs can run without waiting for votes.
+enum ForceCommand {
+    Skip,
+    Stop,
+}
+
 pub struct Frontend {
     pub config: Arc<Config>,
+    pub guild_configs: GuildConfigStore,
+    pub playlists: PlaylistStore,
+    pub library: Library,
     pub backend_brain: Brain,
     pub model: AppModel<Song>,
+    search_cache: SearchCache,
 }
 
 impl Frontend {
     pub fn new(
         config: Arc<Config>,
+        guild_configs: GuildConfigStore,
+        playlists: PlaylistStore,
+        library: Library,
         backend_brain: Brain,
         model: AppModel<Song>,
     ) -> Frontend {
         Frontend {
             config,
+            guild_configs,
+            playlists,
+            library,
             backend_brain,
             model,
+            search_cache: SearchCache::default(),
+        }
+    }
+
+    pub fn embed_color(&self, guild_id: GuildId) -> u32 {
+        self.guild_configs.get(guild_id).embed_color(&self.config)
+    }
+
+    fn source_options(&self, guild_model: &GuildModel<Song>, channel_id: ChannelId) -> SourceOptions {
+        SourceOptions {
+            loudness_target: self.config.loudness_target_lufs,
+            filters: guild_model.channel_filters(channel_id).into_iter().collect(),
+        }
+    }
+
+    fn play_options(&self, guild_model: &GuildModel<Song>, channel_id: ChannelId) -> PlayOptions {
+        PlayOptions {
+            volume: channel_volume(guild_model, channel_id),
+            source_options: self.source_options(guild_model, channel_id),
+            crossfade: self.config.crossfade_secs.filter(|secs| *secs > 0.).map(Duration::from_secs_f64),
         }
     }
 
+    async fn model_delegate(&self, ctx: &Context, guild_id: GuildId) -> Result<ModelDelegate, crate::error::Error> {
+        let dj_role_ids = self.guild_configs.get(guild_id).dj_role_ids(&self.config, guild_id);
+        ModelDelegate::new(ctx, guild_id, dj_role_ids).await
+    }
+
     pub async fn handle_command(
         self: &Arc<Self>,
         ctx: &Context,
         command: &interactions::application_command::ApplicationCommandInteraction
     ) {
-        let send_error_res = match self.handle_command_fallable(ctx, command).await {
+        self.handle_interaction(ctx, InteractionRef::Command(command)).await;
+    }
+
+    pub async fn handle_component(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &interactions::message_component::MessageComponentInteraction
+    ) {
+        // Picking a search result replaces the results with what was queued, and the pause button
+        // switches its message between playing and paused. Other components, like the rest of
+        // the buttons on playing messages, leave their message alone.
+        let replace_message = [SEARCH_SELECT_MENU_ID, PLAYING_PAUSE_BUTTON_ID].contains(&component.data.custom_id.as_str());
+        self.handle_interaction(ctx, InteractionRef::Component { component, replace_message }).await;
+    }
+
+    /// Suggests songs for the `term` option of `/play` and `/replace` as it's being typed.
+    /// Suggestions only come from the guild's history, the user's playlists, recent `/search`
+    /// results and the library, since searching online would be too slow to answer before
+    /// Discord gives up.
+    pub async fn handle_autocomplete(
+        self: &Arc<Self>,
+        ctx: &Context,
+        autocomplete: &interactions::autocomplete::AutocompleteInteraction
+    ) {
+        let term = autocomplete.data.options
+            .iter()
+            .find(|option| option.focused && option.name == "term")
+            .and_then(|option| option.value.as_ref())
+            .and_then(|value| value.as_str());
+        let term = match term {
+            Some(term) => term,
+            None => return,
+        };
+
+        let choices = self.autocomplete_choices(autocomplete.guild_id, autocomplete.user.id, term);
+        let send_res = autocomplete.create_autocomplete_response(&ctx.http, |response| {
+            for (title, url) in choices {
+                response.add_string_choice(title, url);
+            }
+            response
+        }).await;
+
+        if let Err(why) = send_res {
+            log::error!("Error while sending autocomplete response: {}", why);
+        }
+    }
+
+    /// Finds songs whose titles contain the term, as (title, url) pairs.
+    fn autocomplete_choices(&self, maybe_guild_id: Option<GuildId>, user_id: UserId, term: &str) -> Vec<(String, String)> {
+        let lower_term = term.trim().to_lowercase();
+        let mut choices: Vec<(String, String)> = Vec::new();
+        let mut add_choice = |title: &str, url: &str| {
+            let is_new = !choices.iter().any(|(_, choice_url)| choice_url == url);
+            if is_new && url.len() <= MAX_AUTOCOMPLETE_CHOICE_LENGTH && choices.len() < MAX_AUTOCOMPLETE_CHOICES {
+                choices.push((crate::message::truncate(title, MAX_AUTOCOMPLETE_CHOICE_LENGTH), url.to_string()));
+            }
+        };
+
+        // Skip the history instead of waiting if a command is using the guild model. Guilds that
+        // haven't used the bot yet have no history, so no model is created for them.
+        if let Some(guild_model_handle) = maybe_guild_id.and_then(|guild_id| self.model.find(guild_id)) {
+            let maybe_guild_model = guild_model_handle.try_lock();
+            if let Ok(guild_model) = &maybe_guild_model {
+                for played in guild_model.history() {
+                    let metadata = &played.entry.metadata;
+                    if metadata.title.to_lowercase().contains(&lower_term) {
+                        add_choice(&metadata.title, &metadata.url);
+                    }
+                }
+            }
+        }
+
+        for song in self.playlists.songs(user_id) {
+            if song.title.to_lowercase().contains(&lower_term) {
+                add_choice(&song.title, &song.url);
+            }
+        }
+
+        if let Some(guild_id) = maybe_guild_id {
+            for (title, url) in self.search_cache.find(guild_id, &lower_term) {
+                add_choice(&title, &url);
+            }
+        }
+
+        for song in self.library.suggest(term, user_id, MAX_AUTOCOMPLETE_CHOICES) {
+            add_choice(&song.metadata.title, &song.metadata.url);
+        }
+
+        choices
+    }
+
+    async fn handle_interaction(self: &Arc<Self>, ctx: &Context, interaction: InteractionRef<'_>) {
+        let embed_color = match interaction.guild_id() {
+            Some(guild_id) => self.embed_color(guild_id),
+            None => self.config.embed_color,
+        };
+        let send_error_res = match self.handle_interaction_fallable(ctx, interaction).await {
             Ok(_) => Ok(()),
             Err(HandleCommandError::CreateError(why)) => {
                 log::error!("Error while handling command: {}", why);
-                command.create_interaction_response(&ctx.http, |response| {
-                    response.kind(interactions::InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|data| {
-                            data.create_embed(|embed| {
-                                embed
-                                    .description(self.config.get_raw_message("action.unknown_error"))
-                                    .color(self.config.embed_color)
-                            })
-                        })
-                }).await.map(|_| ())
+                let description = self.config.get_raw_message("action.unknown_error").to_string();
+                interaction.respond(&ctx.http, description, embed_color, Vec::new()).await
             }
             Err(HandleCommandError::EditError(why)) => {
                 log::error!("Error while handling command: {}", why);
-                command.edit_original_interaction_response(&ctx.http, |response| {
-                    response.create_embed(|embed| {
-                        embed
-                            .description(self.config.get_raw_message("action.unknown_error"))
-                            .color(self.config.embed_color)
-                    })
-                }).await.map(|_| ())
+                let description = self.config.get_raw_message("action.unknown_error").to_string();
+                interaction.edit_response(&ctx.http, description, embed_color, Vec::new()).await
             }
         };
 
@@ -73,13 +214,13 @@ impl Frontend {
         }
     }
 
-    async fn handle_command_fallable(
+    async fn handle_interaction_fallable(
         self: &Arc<Self>,
         ctx: &Context,
-        command: &interactions::application_command::ApplicationCommandInteraction
+        interaction: InteractionRef<'_>,
     ) -> Result<(), HandleCommandError> {
-        let guild_id = command.guild_id.ok_or(HandleCommandError::CreateError(crate::error::Error::NoGuild))?;
-        let message_channel_id = command.channel_id;
+        let guild_id = interaction.guild_id().ok_or(HandleCommandError::CreateError(crate::error::Error::NoGuild))?;
+        let message_channel_id = interaction.channel_id();
 
         // This signal is used to cancel sending a "loading..." message when we finish executing
         // the command.
@@ -90,9 +231,7 @@ impl Frontend {
                 _ = tokio::time::sleep(Duration::from_millis(SEND_WORKING_TIMEOUT_MS)).fuse() => true,
             );
             if show_deferred_message {
-                if let Err(why) = command.create_interaction_response(&ctx.http, |response| {
-                    response.kind(interactions::InteractionResponseType::DeferredChannelMessageWithSource)
-                }).await {
+                if let Err(why) = interaction.defer(&ctx.http).await {
                     log::error!("Error while sending deferred message: {}", why);
                 }
             }
@@ -105,9 +244,14 @@ impl Frontend {
             guild_model.set_message_channel(Some(message_channel_id));
 
             // Execute the command
-            let messages_res = self
-                .handle_guild_command(ctx, command, guild_id, guild_model.deref_mut())
-                .await;
+            let messages_res = match interaction {
+                InteractionRef::Command(command) => self
+                    .handle_guild_command(ctx, command, guild_id, guild_model.deref_mut())
+                    .await,
+                InteractionRef::Component { component, .. } => self
+                    .handle_guild_component(ctx, component, guild_id, guild_model.deref_mut())
+                    .await,
+            };
 
             // If the timeout has finished, rx will be closed so this send call will return an
             // error. We can use this to know that a response has been created, and we need to edit
@@ -118,9 +262,10 @@ impl Frontend {
 
             let send_res = send_messages(
                 &self.config,
+                self.embed_color(guild_id),
                 ctx,
                 SendMessageDestination::Interaction {
-                    interaction: command,
+                    interaction,
                     is_edit: has_sent_deferred,
                 },
                 guild_model.deref_mut(),
@@ -145,9 +290,10 @@ impl Frontend {
         guild_model: &mut GuildModel<Song>,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
         let user_id = command.user.id;
+        let member = command.member.as_ref();
         match command.data.name.as_str() {
             "play" => {
-                let maybe_term = match command.data.options.get(0).and_then(|val| val.resolved.as_ref()) {
+                let maybe_term = match command.data.options.first().and_then(|val| val.resolved.as_ref()) {
                     Some(application_command::ApplicationCommandInteractionDataOptionValue::String(val)) => Some(val.clone()),
                     _ => None,
                 };
@@ -163,8 +309,136 @@ impl Frontend {
                     }
                 }
             }
+            "search" => {
+                let term = get_string_option(&command.data.options, "term").unwrap_or_default();
+                log::debug!("Received search \"{}\"", term);
+                self.handle_search_command(user_id, guild_id, &term).await
+            }
+            "queue" => {
+                let subcommand = command.data.options.first().ok_or_else(|| crate::error::Error::UnknownCommand("queue".to_string()))?;
+                match subcommand.name.as_str() {
+                    "show" => {
+                        let page = get_integer_option(&subcommand.options, "page").unwrap_or(1);
+                        log::debug!("Received queue show, page {}", page);
+                        self.handle_queue_command(ctx, user_id, guild_id, guild_model, page).await
+                    }
+                    "remove" => {
+                        let position = get_integer_option(&subcommand.options, "position").unwrap_or(0);
+                        log::debug!("Received queue remove, position {}", position);
+                        self.handle_queue_remove_command(ctx, user_id, guild_id, guild_model, position).await
+                    }
+                    "move" => {
+                        let from_position = get_integer_option(&subcommand.options, "from").unwrap_or(0);
+                        let to_position = get_integer_option(&subcommand.options, "to").unwrap_or(0);
+                        log::debug!("Received queue move, from position {} to {}", from_position, to_position);
+                        self.handle_queue_move_command(ctx, user_id, guild_id, guild_model, from_position, to_position).await
+                    }
+                    "clear" => {
+                        log::debug!("Received queue clear");
+                        self.handle_queue_clear_command(ctx, user_id, guild_id, guild_model).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!("queue {}", subcommand_name))),
+                }
+            }
+            "queuemode" => {
+                let maybe_mode = match command.data.options.first().and_then(|val| val.resolved.as_ref()) {
+                    Some(application_command::ApplicationCommandInteractionDataOptionValue::String(val)) => Some(val.clone()),
+                    _ => None,
+                };
+                let has_permission = command.member
+                    .as_ref()
+                    .and_then(|member| member.permissions)
+                    .map(|permissions| permissions.manage_channels())
+                    .unwrap_or(false);
+
+                log::debug!("Received queuemode {:?}", maybe_mode);
+                if has_permission {
+                    self.handle_queue_mode_command(ctx, user_id, guild_id, guild_model, maybe_mode.as_deref()).await
+                } else {
+                    log::trace!("User doesn't have permission to manage channels, queue mode will not change");
+                    Ok(vec![Message::Response(ResponseMessage::MissingPermissionError)])
+                }
+            }
+            "loop" => {
+                let maybe_mode = match command.data.options.first().and_then(|val| val.resolved.as_ref()) {
+                    Some(application_command::ApplicationCommandInteractionDataOptionValue::String(val)) => Some(val.clone()),
+                    _ => None,
+                };
+
+                log::debug!("Received loop {:?}", maybe_mode);
+                self.handle_loop_command(ctx, user_id, member, guild_id, guild_model, maybe_mode.as_deref()).await
+            }
+            "config" => {
+                let subcommand = command.data.options.first().ok_or_else(|| crate::error::Error::UnknownCommand("config".to_string()))?;
+                let has_permission = command.member
+                    .as_ref()
+                    .and_then(|member| member.permissions)
+                    .map(|permissions| permissions.manage_guild())
+                    .unwrap_or(false);
+                if !has_permission {
+                    log::debug!("Received config {}, but user doesn't have permission to manage the guild", subcommand.name);
+                    return Ok(vec![Message::Response(ResponseMessage::MissingPermissionError)]);
+                }
+
+                match subcommand.name.as_str() {
+                    "get" => {
+                        log::debug!("Received config get");
+                        Ok(self.handle_config_get_command(guild_id))
+                    }
+                    "set" => {
+                        let key = get_string_option(&subcommand.options, "key").unwrap_or_default();
+                        let maybe_value = get_string_option(&subcommand.options, "value");
+                        log::debug!("Received config set {} to {:?}", key, maybe_value);
+                        self.handle_config_set_command(guild_id, guild_model, &key, maybe_value.as_deref()).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!("config {}", subcommand_name))),
+                }
+            }
+            "playlist" => {
+                let subcommand = command.data.options.first().ok_or_else(|| crate::error::Error::UnknownCommand("playlist".to_string()))?;
+                let name = get_string_option(&subcommand.options, "name").unwrap_or_default();
+                match subcommand.name.as_str() {
+                    "save" => {
+                        log::debug!("Received playlist save \"{}\"", name);
+                        self.handle_playlist_save_command(ctx, user_id, guild_id, guild_model, &name).await
+                    }
+                    "load" => {
+                        log::debug!("Received playlist load \"{}\"", name);
+                        self.handle_playlist_load_command(ctx, user_id, guild_id, guild_model, &name).await
+                    }
+                    "list" => {
+                        log::debug!("Received playlist list");
+                        Ok(self.handle_playlist_list_command(user_id))
+                    }
+                    "delete" => {
+                        log::debug!("Received playlist delete \"{}\"", name);
+                        self.handle_playlist_delete_command(user_id, &name).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!("playlist {}", subcommand_name))),
+                }
+            }
+            "volume" => {
+                let volume = get_integer_option(&command.data.options, "volume").unwrap_or(100);
+                log::debug!("Received volume {}", volume);
+                self.handle_volume_command(ctx, user_id, member, guild_id, guild_model, volume).await
+            }
+            "filter" => {
+                let name = get_string_option(&command.data.options, "name").unwrap_or_default();
+                log::debug!("Received filter {}", name);
+                self.handle_filter_command(ctx, user_id, member, guild_id, guild_model, &name).await
+            }
+            "history" => {
+                let page = get_integer_option(&command.data.options, "page").unwrap_or(1);
+                log::debug!("Received history, page {}", page);
+                Ok(self.handle_history_command(guild_model, page))
+            }
+            "requeue" => {
+                let position = get_integer_option(&command.data.options, "position").unwrap_or(0);
+                log::debug!("Received requeue, position {}", position);
+                self.handle_requeue_command(ctx, user_id, guild_id, guild_model, position).await
+            }
             "replace" => {
-                let term = match command.data.options.get(0).and_then(|val| val.resolved.as_ref()) {
+                let term = match command.data.options.first().and_then(|val| val.resolved.as_ref()) {
                     Some(application_command::ApplicationCommandInteractionDataOptionValue::String(val)) => val.clone(),
                     _ => "".to_string(),
                 };
@@ -178,16 +452,138 @@ impl Frontend {
             }
             "skip" => {
                 log::debug!("Received skip");
-                self.handle_skip_command(ctx, user_id, guild_id, guild_model).await
+                self.handle_skip_command(ctx, user_id, member, guild_id, guild_model).await
             }
             "stop" => {
                 log::debug!("Received stop");
-                self.handle_stop_command(ctx, user_id, guild_id, guild_model).await
+                self.handle_stop_command(ctx, user_id, member, guild_id, guild_model).await
+            }
+            "seek" => {
+                let timestamp = get_string_option(&command.data.options, "timestamp").unwrap_or_default();
+                log::debug!("Received seek \"{}\"", timestamp);
+                self.handle_seek_command(ctx, user_id, member, guild_id, guild_model, &timestamp).await
+            }
+            "nowplaying" => {
+                log::debug!("Received nowplaying");
+                self.handle_now_playing_command(ctx, user_id, guild_id).await
+            }
+            "forceskip" => {
+                log::debug!("Received forceskip");
+                self.handle_force_command(ctx, user_id, member, guild_id, guild_model, ForceCommand::Skip).await
+            }
+            "forcestop" => {
+                log::debug!("Received forcestop");
+                self.handle_force_command(ctx, user_id, member, guild_id, guild_model, ForceCommand::Stop).await
             }
             command_name => Err(crate::error::Error::UnknownCommand(command_name.to_string())),
         }
     }
 
+    async fn handle_guild_component(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &interactions::message_component::MessageComponentInteraction,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = component.user.id;
+        let member = component.member.as_ref();
+        match component.data.custom_id.as_str() {
+            SEARCH_SELECT_MENU_ID => {
+                let url = component.data.values.first().cloned().unwrap_or_default();
+                log::debug!("Received search result pick \"{}\"", url);
+                self.handle_queue_play_command(ctx, user_id, guild_id, guild_model, &url).await
+            }
+            PLAYING_PAUSE_BUTTON_ID => {
+                log::debug!("Received pause button");
+                if self.is_paused_in_user_channel(ctx, user_id, guild_id).await? {
+                    self.handle_unpause_command(ctx, user_id, guild_id, guild_model).await
+                } else {
+                    self.handle_pause_command(ctx, user_id, guild_id).await
+                }
+            }
+            PLAYING_SKIP_BUTTON_ID => {
+                log::debug!("Received skip button");
+                self.handle_skip_command(ctx, user_id, member, guild_id, guild_model).await
+            }
+            PLAYING_STOP_BUTTON_ID => {
+                log::debug!("Received stop button");
+                self.handle_stop_command(ctx, user_id, member, guild_id, guild_model).await
+            }
+            PLAYING_QUEUE_BUTTON_ID => {
+                log::debug!("Received queue button");
+                self.handle_queue_command(ctx, user_id, guild_id, guild_model, 1).await
+            }
+            custom_id => Err(crate::error::Error::UnknownCommand(custom_id.to_string())),
+        }
+    }
+
+    /// Whether the song playing in the user's voice channel is paused. The pause button resumes
+    /// playback if it is, and pauses it otherwise.
+    async fn is_paused_in_user_channel(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+    ) -> Result<bool, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(false),
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        Ok(guild_speakers_ref
+            .find_active_in_channel(channel_id)
+            .map(|(guild_speaker, _)| guild_speaker.is_paused())
+            .unwrap_or(false))
+    }
+
+    async fn handle_search_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_id: GuildId,
+        term: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let results = match Song::search(&self.config.source_resolvers, term, SEARCH_RESULT_COUNT, user_id).await {
+            Ok(results) => results,
+            Err(mrvn_back_ytdl::Error::NoSongsFound) => {
+                log::trace!("Search found no songs");
+                return Ok(vec![Message::Response(ResponseMessage::NoMatchingSongsError)]);
+            }
+            Err(why) => return Err(crate::error::Error::Backend(why)),
+        };
+
+        // The URL is sent back when a result is picked, and Discord limits how long it can be.
+        let results = results
+            .into_iter()
+            .filter(|result| result.song.metadata.url.len() <= MAX_SELECT_VALUE_LENGTH)
+            .map(|result| SearchResult {
+                song_title: result.song.metadata.title,
+                song_url: result.song.metadata.url,
+                channel: result.channel,
+                duration: result.song.metadata.duration,
+            })
+            .collect::<Vec<_>>();
+        if results.is_empty() {
+            return Ok(vec![Message::Response(ResponseMessage::NoMatchingSongsError)]);
+        }
+
+        log::trace!("Search found {} songs", results.len());
+        self.search_cache.insert(guild_id, results
+            .iter()
+            .map(|result| CachedResult {
+                title: result.song_title.clone(),
+                url: result.song_url.clone(),
+            })
+            .collect());
+        Ok(vec![Message::Response(ResponseMessage::SearchResults {
+            term: term.to_string(),
+            results,
+        })])
+    }
+
     async fn handle_queue_play_command(
         self: &Arc<Self>,
         ctx: &Context,
@@ -196,27 +592,154 @@ impl Frontend {
         guild_model: &mut GuildModel<Song>,
         term: &str,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
-        let delegate_future = ModelDelegate::new(ctx, guild_id);
-        let song_future = async {
-            Song::load(term, user_id).await.map_err(crate::error::Error::Backend)
+        let delegate_future = self.model_delegate(ctx, guild_id);
+        let song_list_future = async {
+            // Library tracks are preferred so they can be played without an internet connection.
+            // They can also be picked with `file://` links.
+            let maybe_song = match self.library.search(term, user_id) {
+                Some(song) => Some(song),
+                None => self.library.get(term, user_id).await,
+            };
+            if let Some(song) = maybe_song {
+                return Ok(SongList::Single(song));
+            }
+            Song::load_list(&self.config.source_resolvers, term, user_id).await.map_err(crate::error::Error::Backend)
         };
 
-        let (delegate, song) = match futures::try_join!(delegate_future, song_future) {
-            Ok((delegate, song)) => (delegate, song),
+        let (delegate, song_list) = match futures::try_join!(delegate_future, song_list_future) {
+            Ok((delegate, song_list)) => (delegate, song_list),
             Err(crate::error::Error::Backend(mrvn_back_ytdl::Error::NoSongsFound)) => {
                 return Ok(vec![Message::Response(ResponseMessage::NoMatchingSongsError)]);
             },
             Err(err) => return Err(err),
         };
 
-        let song_metadata = song.metadata.clone();
-        log::trace!("Resolved song query as {} (\"{}\")", song_metadata.url, song_metadata.title);
+        match song_list {
+            SongList::Single(song) => {
+                log::trace!("Resolved song query as {} (\"{}\")", song.metadata.url, song.metadata.title);
+                self.queue_and_play(ctx, user_id, guild_id, guild_model, &delegate, song).await
+            }
+            SongList::Playlist { title, url, songs } => {
+                log::trace!("Resolved song query as playlist {} (\"{}\") with {} songs", url, title, songs.len());
+                self.queue_list_and_play(ctx, user_id, guild_id, guild_model, &delegate, songs, |count, skipped_count| {
+                    ResponseMessage::QueuedPlaylist {
+                        playlist_title: title,
+                        playlist_url: url,
+                        count,
+                        skipped_count,
+                    }
+                }).await
+            }
+        }
+    }
+
+    /// Adds as many songs from a list to the user's queue as the limits allow, then starts
+    /// playing in their channel if nothing is. The response is created from the number of songs
+    /// that were queued and skipped.
+    #[allow(clippy::too_many_arguments)]
+    async fn queue_list_and_play(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        delegate: &ModelDelegate,
+        songs: Vec<Song>,
+        response: impl FnOnce(usize, usize) -> ResponseMessage,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+        let song_count = songs.len();
+        let mut maybe_first_limit = None;
+        let mut skipped_count = 0;
+        for song in songs {
+            let song_metadata = song.metadata.clone();
+            if let Err(limit) = guild_model.push_entry(user_id, maybe_channel_id, song) {
+                maybe_first_limit.get_or_insert((limit, song_metadata));
+                skipped_count += 1;
+            }
+        }
+
+        // If nothing could be queued, explain why
+        let queued_count = song_count - skipped_count;
+        if queued_count == 0 {
+            if let Some((limit, song_metadata)) = maybe_first_limit {
+                log::trace!("Every song in the list is over a queue limit, nothing will be queued");
+                return Ok(vec![queue_limit_message(limit, song_metadata)]);
+            }
+        }
+        log::trace!("Queued {} songs from the list, {} were over a queue limit", queued_count, skipped_count);
+
+        let mut messages = vec![Message::Response(response(queued_count, skipped_count))];
+
+        // Start playing if the user is in a channel that a speaker can play in, and nothing is
+        // playing there.
+        let channel_id = match maybe_channel_id {
+            Some(channel) => channel,
+            None => {
+                log::trace!("User is not in any voice channel, songs will remain queued");
+                return Ok(messages);
+            }
+        };
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        let guild_speaker = match guild_speakers_ref.find_to_play_in_channel(channel_id) {
+            Some(speaker) => speaker,
+            None => {
+                log::trace!("No speakers are available to handle playback, songs will remain queued");
+                return Ok(messages);
+            }
+        };
+        let next_song = match guild_model.next_channel_entry(delegate, channel_id) {
+            NextEntry::Entry(song) => song,
+            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
+                log::trace!("Channel is already playing, songs will remain queued");
+                return Ok(messages);
+            }
+        };
+
+        let next_metadata = next_song.metadata.clone();
+        log::trace!("Playing \"{}\" to speaker", next_metadata.title);
+        let play_options = self.play_options(guild_model, channel_id);
+        let preload_song = guild_model.peek_next_channel_entry(delegate, channel_id).cloned();
+        guild_speaker.play(channel_id, next_song, play_options, preload_song, EndedDelegate {
+            frontend: self.clone(),
+            ctx: ctx.clone(),
+            guild_id,
+            channel_id,
+        }).await.map_err(|why| {
+            guild_model.end_channel_entry(channel_id);
+            crate::error::Error::Backend(why)
+        })?;
+
+        messages.push(Message::Action(ActionMessage::Playing {
+            song_title: next_metadata.title,
+            song_url: next_metadata.url,
+            voice_channel_id: channel_id,
+            user_id: next_metadata.user_id,
+        }));
+        Ok(messages)
+    }
 
-        guild_model.push_entry(user_id, song);
+    /// Adds a song to the user's queue, then starts playing in their channel if nothing is.
+    async fn queue_and_play(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        delegate: &ModelDelegate,
+        song: Song,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let song_metadata = song.metadata.clone();
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+        if let Err(limit) = guild_model.push_entry(user_id, maybe_channel_id, song) {
+            log::trace!("Song is over a queue limit, it will not be queued");
+            return Ok(vec![queue_limit_message(limit, song_metadata)]);
+        }
 
         // From this point on the user needs to be in a channel, otherwise the song will only stay
         // queued.
-        let channel_id = match delegate.get_user_voice_channel(user_id) {
+        let channel_id = match maybe_channel_id {
             Some(channel) => channel,
             None => {
                 log::trace!("User is not in any voice channel, song will remain queued");
@@ -235,132 +758,693 @@ impl Frontend {
         let guild_speaker = match guild_speakers_ref.find_to_play_in_channel(channel_id) {
             Some(speaker) => speaker,
             None => {
-                log::trace!("No speakers are available to handle playback, song will remain queued");
-                return Ok(vec![Message::Response(ResponseMessage::QueuedNoSpeakers {
-                    song_title: song_metadata.title,
-                    song_url: song_metadata.url,
-                })])
+                log::trace!("No speakers are available to handle playback, song will remain queued");
+                return Ok(vec![Message::Response(ResponseMessage::QueuedNoSpeakers {
+                    song_title: song_metadata.title,
+                    song_url: song_metadata.url,
+                })])
+            }
+        };
+
+        // Play a song if the model indicates one isn't playing.
+        let next_song = match guild_model.next_channel_entry(delegate, channel_id) {
+            NextEntry::Entry(song) => song,
+            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
+                log::trace!("Channel is already playing, song will remain queued");
+                return Ok(vec![Message::Response(ResponseMessage::Queued {
+                    song_title: song_metadata.title,
+                    song_url: song_metadata.url,
+                })])
+            }
+        };
+
+        let next_metadata = next_song.metadata.clone();
+        log::trace!("Playing \"{}\" to speaker", next_metadata.title);
+        let play_options = self.play_options(guild_model, channel_id);
+        let preload_song = guild_model.peek_next_channel_entry(delegate, channel_id).cloned();
+        guild_speaker.play(channel_id, next_song, play_options, preload_song, EndedDelegate {
+            frontend: self.clone(),
+            ctx: ctx.clone(),
+            guild_id,
+            channel_id,
+        }).await.map_err(|why| {
+            guild_model.end_channel_entry(channel_id);
+            crate::error::Error::Backend(why)
+        })?;
+
+        // We could be in one of two states:
+        //  - The song that's now playing is the one we just queued, in which case we only show a
+        //    "playing" message.
+        //  - We queued a song and started a different song, which can happen if there were other
+        //    songs waiting but we weren't playing at the time. In this case we show a "queued"
+        //    message and a "playing" message.
+        if next_metadata.url == song_metadata.url {
+            Ok(vec![Message::Action(ActionMessage::PlayingResponse {
+                song_title: song_metadata.title,
+                song_url: song_metadata.url,
+                voice_channel_id: channel_id,
+            })])
+        } else {
+            Ok(vec![
+                Message::Response(ResponseMessage::Queued {
+                    song_title: song_metadata.title,
+                    song_url: song_metadata.url,
+                }),
+                Message::Action(ActionMessage::Playing {
+                    song_title: next_metadata.title,
+                    song_url: next_metadata.url,
+                    voice_channel_id: channel_id,
+                    user_id: next_metadata.user_id,
+                })
+            ])
+        }
+    }
+
+    async fn handle_unpause_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        // See if there's currently a speaker in this channel to unpause.
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        if let Some((guild_speaker, active_metadata)) = guild_speakers_ref.find_active_in_channel(channel_id) {
+            return if guild_speaker.is_paused() {
+                log::trace!("Found a paused speaker in the user's voice channel, starting playback");
+                guild_speaker.unpause().map_err(crate::error::Error::Backend)?;
+                Ok(vec![Message::Action(ActionMessage::Playing {
+                    song_title: active_metadata.title.clone(),
+                    song_url: active_metadata.url.clone(),
+                    voice_channel_id: channel_id,
+                    user_id: active_metadata.user_id,
+                })])
+            } else {
+                log::trace!("Found an unpaused speaker in the user's voice channel, playback will continue");
+                Ok(vec![Message::Response(ResponseMessage::AlreadyPlayingError {
+                    voice_channel_id: channel_id,
+                })])
+            };
+        };
+
+        // Otherwise, try starting to play in this channel.
+        let guild_speaker = match guild_speakers_ref.find_to_play_in_channel(channel_id) {
+            Some(speaker) => speaker,
+            None => {
+                log::trace!("No speakers are available to handle playback, nothing will be played");
+                return Ok(vec![Message::Action(ActionMessage::NoSpeakersError {
+                    voice_channel_id: channel_id,
+                })])
+            },
+        };
+        let next_song = match guild_model.next_channel_entry(&delegate, channel_id) {
+            NextEntry::Entry(song) => song,
+            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
+                log::trace!("No songs are available to play back in the channel, nothing will be played");
+                return Ok(vec![Message::Response(ResponseMessage::NothingIsQueuedError {
+                    voice_channel_id: channel_id,
+                })])
+            }
+        };
+
+        let next_metadata = next_song.metadata.clone();
+        log::trace!("Playing \"{}\" to speaker", next_metadata.title);
+        let play_options = self.play_options(guild_model, channel_id);
+        let preload_song = guild_model.peek_next_channel_entry(&delegate, channel_id).cloned();
+        guild_speaker.play(channel_id, next_song, play_options, preload_song, EndedDelegate {
+            frontend: self.clone(),
+            ctx: ctx.clone(),
+            guild_id,
+            channel_id,
+        }).await.map_err(|why| {
+            guild_model.end_channel_entry(channel_id);
+            crate::error::Error::Backend(why)
+        })?;
+
+        Ok(vec![Message::Action(ActionMessage::Playing {
+            song_title: next_metadata.title,
+            song_url: next_metadata.url,
+            voice_channel_id: channel_id,
+            user_id: next_metadata.user_id,
+        })])
+    }
+
+    async fn handle_queue_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        page: i64,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+
+        let user_songs: Vec<_> = guild_model
+            .user_entries(user_id, maybe_channel_id)
+            .map(|song| QueuedSong {
+                song_title: song.metadata.title.clone(),
+                song_url: song.metadata.url.clone(),
+                user_id: song.metadata.user_id,
+            })
+            .collect();
+
+        // The upcoming songs and currently playing song are only shown if the user is in a
+        // channel, since that determines which channel's order we show.
+        let (playing, upcoming_songs) = match maybe_channel_id {
+            Some(channel_id) => {
+                let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+                let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+                let playing = guild_speakers_ref
+                    .find_active_in_channel(channel_id)
+                    .map(|(_, active_metadata)| QueuedSong {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        user_id: active_metadata.user_id,
+                    });
+                let upcoming_songs: Vec<_> = guild_model
+                    .upcoming_channel_entries(&delegate, channel_id)
+                    .into_iter()
+                    .map(|upcoming| QueuedSong {
+                        song_title: upcoming.entry.metadata.title.clone(),
+                        song_url: upcoming.entry.metadata.url.clone(),
+                        user_id: upcoming.user_id,
+                    })
+                    .collect();
+                (playing, upcoming_songs)
+            }
+            None => (None, Vec::new()),
+        };
+
+        let song_count = user_songs.len().max(upcoming_songs.len());
+        let page_count = song_count.div_ceil(QUEUE_PAGE_SIZE).max(1);
+        let page = (page.max(1) as usize).min(page_count);
+        let first_index = (page - 1) * QUEUE_PAGE_SIZE;
+
+        log::trace!("Showing queue page {} of {}", page, page_count);
+        Ok(vec![Message::Response(ResponseMessage::QueueList {
+            playing,
+            user_songs: user_songs.into_iter().skip(first_index).take(QUEUE_PAGE_SIZE).collect(),
+            upcoming_songs: upcoming_songs.into_iter().skip(first_index).take(QUEUE_PAGE_SIZE).collect(),
+            first_position: first_index + 1,
+            voice_channel_id: maybe_channel_id,
+            page,
+            page_count,
+        })])
+    }
+
+    async fn handle_queue_remove_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        position: i64,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+
+        let maybe_removed_song = position_to_index(position)
+            .and_then(|index| guild_model.remove_entry(user_id, maybe_channel_id, index));
+        match maybe_removed_song {
+            Some(song) => {
+                log::trace!("Removed \"{}\" from the user's queue", song.metadata.title);
+                Ok(vec![Message::Response(ResponseMessage::QueueRemoved {
+                    song_title: song.metadata.title,
+                    song_url: song.metadata.url,
+                    position: position as usize,
+                })])
+            }
+            None => {
+                log::trace!("No song is at the position in the user's queue, nothing will be removed");
+                Ok(vec![Message::Response(ResponseMessage::QueuePositionError {
+                    position,
+                })])
+            }
+        }
+    }
+
+    async fn handle_queue_move_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        from_position: i64,
+        to_position: i64,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+
+        let maybe_from_index = position_to_index(from_position);
+        let to_index = position_to_index(to_position).unwrap_or(0);
+        let maybe_moved = maybe_from_index
+            .and_then(|from_index| guild_model.move_entry(user_id, maybe_channel_id, from_index, to_index))
+            .map(|song| song.metadata.clone());
+        match maybe_moved {
+            Some(metadata) => {
+                log::trace!("Moved \"{}\" in the user's queue", metadata.title);
+                let new_position = guild_model.user_entries(user_id, maybe_channel_id).count().min(to_index + 1);
+                Ok(vec![Message::Response(ResponseMessage::QueueMoved {
+                    song_title: metadata.title,
+                    song_url: metadata.url,
+                    old_position: from_position as usize,
+                    new_position,
+                })])
+            }
+            None => {
+                log::trace!("No song is at the position in the user's queue, nothing will be moved");
+                Ok(vec![Message::Response(ResponseMessage::QueuePositionError {
+                    position: from_position,
+                })])
+            }
+        }
+    }
+
+    async fn handle_queue_clear_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+
+        match guild_model.clear_entries(user_id, maybe_channel_id) {
+            0 => {
+                log::trace!("User's queue is already empty, nothing will be cleared");
+                Ok(vec![Message::Response(ResponseMessage::QueueEmptyError)])
+            }
+            count => {
+                log::trace!("Cleared {} songs from the user's queue", count);
+                Ok(vec![Message::Response(ResponseMessage::QueueCleared {
+                    count,
+                })])
+            }
+        }
+    }
+
+    async fn handle_queue_mode_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        mode: Option<&str>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        let maybe_queue_mode = match mode {
+            Some("per_user") => Some(QueueMode::PerUser),
+            Some("shared") => Some(QueueMode::Shared),
+            _ => None,
+        };
+        guild_model.set_channel_queue_mode(channel_id, maybe_queue_mode);
+
+        let queue_mode = guild_model.channel_queue_mode(channel_id);
+        log::trace!("Channel queue mode is now {:?}", queue_mode);
+        Ok(vec![Message::Response(ResponseMessage::QueueModeChanged {
+            voice_channel_id: channel_id,
+            is_shared: queue_mode == QueueMode::Shared,
+        })])
+    }
+
+    async fn handle_loop_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        member: Option<&Member>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        mode: Option<&str>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        let repeat_mode = match mode {
+            Some("song") => RepeatMode::Song,
+            Some("queue") => RepeatMode::Queue,
+            _ => RepeatMode::Off,
+        };
+        let maybe_vote_message = vote_for_setting(
+            guild_model,
+            &delegate,
+            VoteType::Loop(repeat_mode),
+            channel_id,
+            user_id,
+            |voice_channel_id, count| ResponseMessage::LoopMoreVotesNeeded { voice_channel_id, count },
+            |voice_channel_id| ResponseMessage::LoopAlreadyVotedError { voice_channel_id },
+        );
+        if let Some(vote_message) = maybe_vote_message {
+            return Ok(vec![vote_message]);
+        }
+
+        guild_model.set_channel_repeat_mode(channel_id, repeat_mode);
+
+        log::trace!("Channel repeat mode is now {:?}", repeat_mode);
+        Ok(vec![Message::Response(ResponseMessage::LoopModeChanged {
+            voice_channel_id: channel_id,
+            mode: repeat_mode,
+        })])
+    }
+
+    async fn handle_volume_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        member: Option<&Member>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        volume: i64,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        if self.guild_configs.get(guild_id).volume_requires_dj(&self.config) && !delegate.is_user_privileged(user_id) {
+            log::trace!("Volume is restricted to DJs and user doesn't have a DJ role, volume will not change");
+            return Ok(vec![Message::Response(ResponseMessage::MissingPermissionError)]);
+        }
+
+        let volume = match volume {
+            volume if (0..=MAX_VOLUME as i64).contains(&volume) => volume as u32,
+            _ => {
+                log::trace!("Volume is out of range, volume will not change");
+                return Ok(vec![Message::Response(ResponseMessage::VolumeRangeError {
+                    max_volume: MAX_VOLUME,
+                })]);
+            }
+        };
+
+        // Keep the volume in the model so it carries over to every song played in the channel.
+        let model_volume = match volume {
+            100 => None,
+            _ => Some(volume),
+        };
+        guild_model.set_channel_volume(channel_id, model_volume);
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        if let Some((guild_speaker, _)) = guild_speakers_ref.find_active_in_channel(channel_id) {
+            log::trace!("Found a speaker in the user's voice channel, changing the current song's volume");
+            guild_speaker.set_volume(channel_volume(guild_model, channel_id)).map_err(crate::error::Error::Backend)?;
+        }
+
+        Ok(vec![Message::Response(ResponseMessage::VolumeChanged {
+            voice_channel_id: channel_id,
+            volume,
+        })])
+    }
+
+    async fn handle_filter_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        member: Option<&Member>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        let maybe_filter = match name {
+            "bassboost" => Some(AudioFilter::BassBoost),
+            "speed" => Some(AudioFilter::Speed),
+            "nightcore" => Some(AudioFilter::Nightcore),
+            "8d" => Some(AudioFilter::EightD),
+            _ => None,
+        };
+        let maybe_vote_message = vote_for_setting(
+            guild_model,
+            &delegate,
+            VoteType::Filter(maybe_filter),
+            channel_id,
+            user_id,
+            |voice_channel_id, count| ResponseMessage::FilterMoreVotesNeeded { voice_channel_id, count },
+            |voice_channel_id| ResponseMessage::FilterAlreadyVotedError { voice_channel_id },
+        );
+        if let Some(vote_message) = maybe_vote_message {
+            return Ok(vec![vote_message]);
+        }
+
+        let mut filters = guild_model.channel_filters(channel_id);
+        match maybe_filter {
+            // Naming a filter turns it on or off, anything else turns them all off.
+            Some(filter) => {
+                if !filters.remove(&filter) {
+                    filters.insert(filter);
+                }
+            }
+            None => filters.clear(),
+        }
+        guild_model.set_channel_filters(channel_id, filters.clone());
+        log::trace!("Channel filters are now {:?}", filters);
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        if let Some((guild_speaker, _)) = guild_speakers_ref.find_active_in_channel(channel_id) {
+            log::trace!("Found a speaker in the user's voice channel, restarting the current song with the new filters");
+            guild_speaker.set_source_options(self.source_options(guild_model, channel_id)).await.map_err(crate::error::Error::Backend)?;
+        }
+
+        Ok(vec![Message::Response(ResponseMessage::FiltersChanged {
+            voice_channel_id: channel_id,
+            filters: filters.into_iter().collect(),
+        })])
+    }
+
+    fn handle_config_get_command(&self, guild_id: GuildId) -> Vec<crate::message::Message> {
+        let entries = self.guild_configs
+            .get(guild_id)
+            .describe(&self.config, guild_id)
+            .into_iter()
+            .map(|(key, value, is_overridden)| ConfigEntry {
+                key: key.to_string(),
+                value,
+                is_overridden,
+            })
+            .collect();
+        vec![Message::Response(ResponseMessage::ConfigList { entries })]
+    }
+
+    async fn handle_config_set_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_config = match self.guild_configs.get(guild_id).with_value(key, value) {
+            Some(guild_config) => guild_config,
+            None => {
+                log::trace!("Config key or value is invalid, config will not change");
+                return Ok(vec![Message::Response(ResponseMessage::ConfigInvalidError {
+                    key: key.to_string(),
+                })]);
+            }
+        };
+
+        // Guild models are created with the config from the app model, so it's updated as well
+        // as the current guild model.
+        let model_config = guild_config.model_config(&self.config);
+        self.model.set_guild_config(guild_id, model_config);
+        guild_model.set_config(model_config);
+        self.guild_configs.set(guild_id, guild_config).await?;
+
+        let (value_string, is_overridden) = self.guild_configs
+            .get(guild_id)
+            .describe(&self.config, guild_id)
+            .into_iter()
+            .find(|(entry_key, _, _)| *entry_key == key)
+            .map(|(_, value, is_overridden)| (value, is_overridden))
+            .unwrap_or_default();
+        log::trace!("Guild config {} is now {}", key, value_string);
+        Ok(vec![Message::Response(ResponseMessage::ConfigChanged {
+            key: key.to_string(),
+            value: value_string,
+            is_overridden,
+        })])
+    }
+
+    async fn handle_playlist_save_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let maybe_channel_id = delegate.get_user_voice_channel(user_id);
+
+        let songs: Vec<_> = guild_model
+            .user_entries(user_id, maybe_channel_id)
+            .map(|song| SavedSong {
+                title: song.metadata.title.clone(),
+                url: song.metadata.url.clone(),
+                duration: song.metadata.duration,
+            })
+            .collect();
+        if songs.is_empty() {
+            log::trace!("User's queue is empty, there's nothing to save");
+            return Ok(vec![Message::Response(ResponseMessage::QueueEmptyError)]);
+        }
+
+        let count = songs.len();
+        self.playlists.save(user_id, name, songs).await?;
+        log::trace!("Saved {} songs to the user's playlist \"{}\"", count, name);
+        Ok(vec![Message::Response(ResponseMessage::PlaylistSaved {
+            name: name.to_string(),
+            count,
+        })])
+    }
+
+    async fn handle_playlist_load_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let saved_songs = match self.playlists.get(user_id, name) {
+            Some(saved_songs) => saved_songs,
+            None => {
+                log::trace!("User doesn't have a playlist called \"{}\"", name);
+                return Ok(vec![Message::Response(ResponseMessage::PlaylistNotFoundError {
+                    name: name.to_string(),
+                })]);
             }
         };
 
-        // Play a song if the model indicates one isn't playing.
-        let next_song = match guild_model.next_channel_entry(&delegate, channel_id) {
-            NextEntry::Entry(song) => song,
-            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
-                log::trace!("Channel is already playing, song will remain queued");
-                return Ok(vec![Message::Response(ResponseMessage::Queued {
-                    song_title: song_metadata.title,
-                    song_url: song_metadata.url,
-                })])
+        // Saved songs were already resolved, so they're only opened when they play
+        let songs = future::join_all(saved_songs
+            .into_iter()
+            .map(|saved_song| async move {
+                match self.library.get(&saved_song.url, user_id).await {
+                    Some(song) => song,
+                    None => Song::from_metadata(&self.config.source_resolvers, SongMetadata {
+                        title: saved_song.title,
+                        url: saved_song.url,
+                        user_id,
+                        duration: saved_song.duration,
+                    }),
+                }
+            }))
+            .await;
+
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        self.queue_list_and_play(ctx, user_id, guild_id, guild_model, &delegate, songs, |count, skipped_count| {
+            ResponseMessage::PlaylistLoaded {
+                name: name.to_string(),
+                count,
+                skipped_count,
             }
-        };
+        }).await
+    }
 
-        let next_metadata = next_song.metadata.clone();
-        log::trace!("Playing \"{}\" to speaker", next_metadata.title);
-        guild_speaker.play(channel_id, next_song, EndedDelegate {
-            frontend: self.clone(),
-            ctx: ctx.clone(),
-            guild_id,
-            channel_id,
-        }).await.map_err(crate::error::Error::Backend)?;
+    fn handle_playlist_list_command(&self, user_id: UserId) -> Vec<crate::message::Message> {
+        vec![Message::Response(ResponseMessage::PlaylistList {
+            playlists: self.playlists.list(user_id),
+        })]
+    }
 
-        // We could be in one of two states:
-        //  - The song that's now playing is the one we just queued, in which case we only show a
-        //    "playing" message.
-        //  - We queued a song and started a different song, which can happen if there were other
-        //    songs waiting but we weren't playing at the time. In this case we show a "queued"
-        //    message and a "playing" message.
-        if next_metadata.url == song_metadata.url {
-            Ok(vec![Message::Action(ActionMessage::PlayingResponse {
-                song_title: song_metadata.title,
-                song_url: song_metadata.url,
-                voice_channel_id: channel_id,
+    async fn handle_playlist_delete_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if self.playlists.delete(user_id, name).await? {
+            log::trace!("Deleted the user's playlist \"{}\"", name);
+            Ok(vec![Message::Response(ResponseMessage::PlaylistDeleted {
+                name: name.to_string(),
             })])
         } else {
-            Ok(vec![
-                Message::Response(ResponseMessage::Queued {
-                    song_title: song_metadata.title,
-                    song_url: song_metadata.url,
-                }),
-                Message::Action(ActionMessage::Playing {
-                    song_title: next_metadata.title,
-                    song_url: next_metadata.url,
-                    voice_channel_id: channel_id,
-                    user_id: next_metadata.user_id,
-                })
-            ])
+            log::trace!("User doesn't have a playlist called \"{}\"", name);
+            Ok(vec![Message::Response(ResponseMessage::PlaylistNotFoundError {
+                name: name.to_string(),
+            })])
         }
     }
 
-    async fn handle_unpause_command(
+    fn handle_history_command(
+        &self,
+        guild_model: &GuildModel<Song>,
+        page: i64,
+    ) -> Vec<crate::message::Message> {
+        let song_count = guild_model.history().count();
+        let page_count = song_count.div_ceil(QUEUE_PAGE_SIZE).max(1);
+        let page = (page.max(1) as usize).min(page_count);
+        let first_index = (page - 1) * QUEUE_PAGE_SIZE;
+
+        let songs: Vec<_> = guild_model
+            .history()
+            .skip(first_index)
+            .take(QUEUE_PAGE_SIZE)
+            .map(|played| PlayedSong {
+                song_title: played.entry.metadata.title.clone(),
+                song_url: played.entry.metadata.url.clone(),
+                user_id: played.user_id,
+                voice_channel_id: played.channel_id,
+                started_time: played.started_time,
+                skipped: played.skipped,
+            })
+            .collect();
+
+        log::trace!("Showing history page {} of {}", page, page_count);
+        vec![Message::Response(ResponseMessage::HistoryList {
+            songs,
+            first_position: first_index + 1,
+            page,
+            page_count,
+        })]
+    }
+
+    async fn handle_requeue_command(
         self: &Arc<Self>,
         ctx: &Context,
         user_id: UserId,
         guild_id: GuildId,
         guild_model: &mut GuildModel<Song>,
+        position: i64,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
-        let delegate = ModelDelegate::new(ctx, guild_id).await?;
-        let channel_id = match delegate.get_user_voice_channel(user_id) {
-            Some(channel) => channel,
-            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
-        };
-
-        // See if there's currently a speaker in this channel to unpause.
-        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
-        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
-        if let Some((guild_speaker, active_metadata)) = guild_speakers_ref.find_active_in_channel(channel_id) {
-            return if guild_speaker.is_paused() {
-                log::trace!("Found a paused speaker in the user's voice channel, starting playback");
-                guild_speaker.unpause().map_err(crate::error::Error::Backend)?;
-                Ok(vec![Message::Action(ActionMessage::Playing {
-                    song_title: active_metadata.title.clone(),
-                    song_url: active_metadata.url.clone(),
-                    voice_channel_id: channel_id,
-                    user_id: active_metadata.user_id,
-                })])
-            } else {
-                log::trace!("Found an unpaused speaker in the user's voice channel, playback will continue");
-                Ok(vec![Message::Response(ResponseMessage::AlreadyPlayingError {
-                    voice_channel_id: channel_id,
-                })])
-            };
-        };
-
-        // Otherwise, try starting to play in this channel.
-        let guild_speaker = match guild_speakers_ref.find_to_play_in_channel(channel_id) {
-            Some(speaker) => speaker,
+        let maybe_played_song = position_to_index(position)
+            .and_then(|index| guild_model.history_entry(index));
+        let mut song = match maybe_played_song {
+            Some(played) => played.entry.clone(),
             None => {
-                log::trace!("No speakers are available to handle playback, nothing will be played");
-                return Ok(vec![Message::Action(ActionMessage::NoSpeakersError {
-                    voice_channel_id: channel_id,
-                })])
-            },
-        };
-        let next_song = match guild_model.next_channel_entry(&delegate, channel_id) {
-            NextEntry::Entry(song) => song,
-            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
-                log::trace!("No songs are available to play back in the channel, nothing will be played");
-                return Ok(vec![Message::Response(ResponseMessage::NothingIsQueuedError {
-                    voice_channel_id: channel_id,
-                })])
+                log::trace!("There's no song at position {} in the history", position);
+                return Ok(vec![Message::Response(ResponseMessage::HistoryPositionError { position })]);
             }
         };
 
-        let next_metadata = next_song.metadata.clone();
-        log::trace!("Playing \"{}\" to speaker", next_metadata.title);
-        guild_speaker.play(channel_id, next_song, EndedDelegate {
-            frontend: self.clone(),
-            ctx: ctx.clone(),
-            guild_id,
-            channel_id,
-        }).await.map_err(crate::error::Error::Backend)?;
+        // The song now belongs to whoever requeued it
+        song.metadata.user_id = user_id;
+        log::trace!("Requeueing \"{}\" from the history", song.metadata.title);
 
-        Ok(vec![Message::Action(ActionMessage::Playing {
-            song_title: next_metadata.title,
-            song_url: next_metadata.url,
-            voice_channel_id: channel_id,
-            user_id: next_metadata.user_id,
-        })])
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        self.queue_and_play(ctx, user_id, guild_id, guild_model, &delegate, song).await
     }
 
     async fn handle_replace_command(
@@ -371,9 +1455,16 @@ impl Frontend {
         guild_model: &mut GuildModel<Song>,
         term: &str,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
-        let delegate_future = ModelDelegate::new(ctx, guild_id);
+        let delegate_future = self.model_delegate(ctx, guild_id);
         let song_future = async {
-            Song::load(term, user_id).await.map_err(crate::error::Error::Backend)
+            let maybe_song = match self.library.search(term, user_id) {
+                Some(song) => Some(song),
+                None => self.library.get(term, user_id).await,
+            };
+            if let Some(song) = maybe_song {
+                return Ok(song);
+            }
+            Song::load(&self.config.source_resolvers, term, user_id).await.map_err(crate::error::Error::Backend)
         };
 
         let (delegate, song) = match futures::try_join!(delegate_future, song_future) {
@@ -388,7 +1479,14 @@ impl Frontend {
         log::trace!("Resolved song query as {} (\"{}\")", song_metadata.url, song_metadata.title);
 
         let maybe_channel_id = delegate.get_user_voice_channel(user_id);
-        let channel_id = match guild_model.replace_entry(user_id, maybe_channel_id, song) {
+        let replace_status = match guild_model.replace_entry(user_id, maybe_channel_id, song) {
+            Ok(replace_status) => replace_status,
+            Err(limit) => {
+                log::trace!("Song is over a queue limit, it will not replace anything");
+                return Ok(vec![queue_limit_message(limit, song_metadata)]);
+            }
+        };
+        let channel_id = match replace_status {
             // If the song was queued, no playback changes are needed so we send a status message
             // and leave it there. But if the model indicated we're replacing the current song,
             // we need to start playing the next song.
@@ -434,12 +1532,17 @@ impl Frontend {
 
         let next_metadata = next_song.metadata.clone();
         log::trace!("Playing \"{}\" to speaker", next_metadata.title);
-        guild_speaker.play(channel_id, next_song, EndedDelegate {
+        let play_options = self.play_options(guild_model, channel_id);
+        let preload_song = guild_model.peek_next_channel_entry(&delegate, channel_id).cloned();
+        guild_speaker.play(channel_id, next_song, play_options, preload_song, EndedDelegate {
             frontend: self.clone(),
             ctx: ctx.clone(),
             guild_id,
             channel_id,
-        }).await.map_err(crate::error::Error::Backend)?;
+        }).await.map_err(|why| {
+            guild_model.end_channel_entry(channel_id);
+            crate::error::Error::Backend(why)
+        })?;
 
         // We could be in one of two states:
         //  - The song that's now playing is the one we just queued, in which case we only show a
@@ -478,7 +1581,7 @@ impl Frontend {
         user_id: UserId,
         guild_id: GuildId,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
-        let delegate = ModelDelegate::new(ctx, guild_id).await?;
+        let delegate = self.model_delegate(ctx, guild_id).await?;
         let channel_id = match delegate.get_user_voice_channel(user_id) {
             Some(channel) => channel,
             None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
@@ -517,10 +1620,11 @@ impl Frontend {
         self: &Arc<Self>,
         ctx: &Context,
         user_id: UserId,
+        member: Option<&Member>,
         guild_id: GuildId,
         guild_model: &mut GuildModel<Song>,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
-        let delegate = ModelDelegate::new(&ctx, guild_id).await?;
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
         let channel_id = match delegate.get_user_voice_channel(user_id) {
             Some(channel) => channel,
             None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
@@ -574,10 +1678,11 @@ impl Frontend {
         self: &Arc<Self>,
         ctx: &Context,
         user_id: UserId,
+        member: Option<&Member>,
         guild_id: GuildId,
         guild_model: &mut GuildModel<Song>,
     ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
-        let delegate = ModelDelegate::new(&ctx, guild_id).await?;
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
         let channel_id = match delegate.get_user_voice_channel(user_id) {
             Some(channel) => channel,
             None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
@@ -625,6 +1730,161 @@ impl Frontend {
         }
     }
 
+    async fn handle_seek_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        member: Option<&Member>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        timestamp: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        let position = match parse_timestamp(timestamp) {
+            Some(position) => position,
+            None => {
+                log::trace!("Timestamp couldn't be parsed, playback will not change");
+                return Ok(vec![Message::Response(ResponseMessage::InvalidTimestampError {
+                    timestamp: timestamp.to_string(),
+                })]);
+            }
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        let (guild_speaker, active_metadata) = match guild_speakers_ref.find_active_in_channel(channel_id) {
+            Some(active) => active,
+            None => {
+                log::trace!("No speakers are in the user's voice channel, playback will not change");
+                return Ok(vec![Message::Response(ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                })]);
+            }
+        };
+
+        // Check the seek can actually happen before counting a vote for it.
+        if !guild_speaker.is_seekable() {
+            log::trace!("The current song can't be seeked, playback will not change");
+            return Ok(vec![Message::Response(ResponseMessage::SeekUnsupportedError {
+                song_title: active_metadata.title,
+                song_url: active_metadata.url,
+            })]);
+        }
+        if let Some(duration) = active_metadata.duration {
+            if position >= duration {
+                log::trace!("Timestamp is past the end of the current song, playback will not change");
+                return Ok(vec![Message::Response(ResponseMessage::SeekPastEndError {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    duration,
+                })]);
+            }
+        }
+
+        match guild_model.vote_for_skip(&delegate, VoteType::Seek(position), channel_id, user_id) {
+            VoteStatus::Success => {
+                log::trace!("Seek command passed preconditions, seeking current playback");
+                guild_speaker.seek(position).await.map_err(crate::error::Error::Backend)?;
+                Ok(vec![Message::Response(ResponseMessage::Seeked {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: channel_id,
+                    position,
+                })])
+            }
+            VoteStatus::AlreadyVoted => {
+                log::trace!("User attempting to seek has already voted, not seeking playback");
+                Ok(vec![Message::Response(ResponseMessage::SeekAlreadyVotedError {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: channel_id,
+                })])
+            }
+            VoteStatus::NeedsMoreVotes(count) => {
+                log::trace!("Seek vote has been counted but more are needed, not seeking playback");
+                Ok(vec![Message::Response(ResponseMessage::SeekMoreVotesNeeded {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: channel_id,
+                    count,
+                })])
+            }
+            VoteStatus::NothingPlaying => Err(crate::error::Error::ModelPlayingSpeakerNotDesync),
+        }
+    }
+
+    async fn handle_now_playing_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+        let channel_id = match delegate.get_user_voice_channel(user_id) {
+            Some(channel) => channel,
+            None => return Ok(vec![Message::Response(ResponseMessage::NotInVoiceChannelError)])
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock().await;
+        let maybe_position = match guild_speakers_ref.find_active_in_channel(channel_id) {
+            Some((guild_speaker, active_metadata)) => guild_speaker
+                .position()
+                .await
+                .map_err(crate::error::Error::Backend)?
+                .map(|position| (position, active_metadata)),
+            None => None,
+        };
+
+        match maybe_position {
+            Some((position, active_metadata)) => {
+                log::trace!("Found a speaker in the user's voice channel at {:?}", position);
+                Ok(vec![Message::Response(ResponseMessage::NowPlaying {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: channel_id,
+                    user_id: active_metadata.user_id,
+                    position,
+                    duration: active_metadata.duration,
+                })])
+            }
+            None => {
+                log::trace!("No speakers are playing in the user's voice channel");
+                Ok(vec![Message::Response(ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                })])
+            }
+        }
+    }
+
+    async fn handle_force_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        member: Option<&Member>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<Song>,
+        command: ForceCommand,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delegate = self.model_delegate(ctx, guild_id).await?.with_member(member);
+        if !delegate.is_user_privileged(user_id) {
+            log::trace!("User doesn't have a DJ role, playback will not change");
+            return Ok(vec![Message::Response(ResponseMessage::MissingPermissionError)]);
+        }
+
+        // Votes from privileged users always succeed, so these behave the same as the regular
+        // commands.
+        match command {
+            ForceCommand::Skip => self.handle_skip_command(ctx, user_id, member, guild_id, guild_model).await,
+            ForceCommand::Stop => self.handle_stop_command(ctx, user_id, member, guild_id, guild_model).await,
+        }
+    }
+
     async fn handle_playback_ended(self: Arc<Self>, ctx: Context, guild_id: GuildId, channel_id: ChannelId, ended_handle: GuildSpeakerEndedHandle) {
         log::trace!("Playback has ended, preparing to play the next available song");
 
@@ -635,11 +1895,11 @@ impl Frontend {
         let messages = self.continue_channel_playback(&ctx, guild_id, guild_model.deref_mut(), channel_id, ended_handle).await;
         let send_result = match (messages, maybe_message_channel) {
             (Ok(messages), Some(message_channel)) => {
-                send_messages(&self.config, &ctx, SendMessageDestination::Channel(message_channel), guild_model.deref_mut(), messages).await
+                send_messages(&self.config, self.embed_color(guild_id), &ctx, SendMessageDestination::Channel(message_channel), guild_model.deref_mut(), messages).await
             },
             (Err(why), Some(message_channel)) => {
                 log::error!("Error while continuing playback: {}", why);
-                send_messages(&self.config, &ctx, SendMessageDestination::Channel(message_channel), guild_model.deref_mut(), vec![
+                send_messages(&self.config, self.embed_color(guild_id), &ctx, SendMessageDestination::Channel(message_channel), guild_model.deref_mut(), vec![
                     Message::Action(ActionMessage::UnknownError)
                 ]).await
             },
@@ -666,37 +1926,158 @@ impl Frontend {
             return Ok(Vec::new());
         }
 
-        let delegate = ModelDelegate::new(&ctx, guild_id).await?;
-        match guild_model.next_channel_entry_finished(&delegate, channel_id) {
-            Some(song) => {
-                let next_metadata = song.metadata.clone();
-                log::trace!("Playing \"{}\" to speaker", next_metadata.title);
-                ended_handle.play(channel_id, song, EndedDelegate {
+        let delegate = self.model_delegate(ctx, guild_id).await?;
+
+        // A song that starts crossfading with nothing to fade into keeps playing until it ends,
+        // and anything queued by then plays afterwards.
+        let ended_handle = match ended_handle.is_fading() && guild_model.peek_next_channel_entry(&delegate, channel_id).is_none() {
+            true => {
+                let wait_result = ended_handle.wait_for_end(EndedDelegate {
                     frontend: self.clone(),
                     ctx: ctx.clone(),
                     guild_id,
                     channel_id,
-                }).await.map_err(crate::error::Error::Backend)?;
-
-                Ok(vec![Message::Action(ActionMessage::Playing {
-                    song_title: next_metadata.title,
-                    song_url: next_metadata.url,
-                    voice_channel_id: channel_id,
-                    user_id: next_metadata.user_id,
-                })])
+                }).await;
+                match wait_result {
+                    Ok(()) => {
+                        log::trace!("Nothing to crossfade into, waiting for the song to end");
+                        return Ok(Vec::new());
+                    }
+                    Err(ended_handle) => ended_handle,
+                }
             }
-            None => {
-                log::trace!("No songs are available to play in the channel, nothing will be played");
+            false => ended_handle,
+        };
 
-                ended_handle.stop().await;
-                return Ok(vec![Message::Action(ActionMessage::Finished {
-                    voice_channel_id: channel_id,
-                })])
+        loop {
+            let song = match guild_model.next_channel_entry_finished(&delegate, channel_id) {
+                Some(song) => song,
+                None => {
+                    log::trace!("No songs are available to play in the channel, nothing will be played");
+
+                    ended_handle.stop().await;
+                    return Ok(vec![Message::Action(ActionMessage::Finished {
+                        voice_channel_id: channel_id,
+                    })]);
+                }
+            };
+
+            let next_metadata = song.metadata.clone();
+            log::trace!("Playing \"{}\" to speaker", next_metadata.title);
+            let play_options = self.play_options(guild_model, channel_id);
+            let preload_song = guild_model.peek_next_channel_entry(&delegate, channel_id).cloned();
+            let play_result = ended_handle.play(channel_id, song, play_options, preload_song, EndedDelegate {
+                frontend: self.clone(),
+                ctx: ctx.clone(),
+                guild_id,
+                channel_id,
+            }).await;
+
+            match play_result {
+                Ok(()) => {
+                    return Ok(vec![Message::Action(ActionMessage::Playing {
+                        song_title: next_metadata.title,
+                        song_url: next_metadata.url,
+                        voice_channel_id: channel_id,
+                        user_id: next_metadata.user_id,
+                    })]);
+                }
+                Err(why) => {
+                    // Songs are only opened when they play, so this can fail for a song that
+                    // queued fine. Skip it instead of leaving the channel stuck on it.
+                    log::error!("Error while playing \"{}\", skipping it: {}", next_metadata.title, why);
+                    guild_model.end_channel_entry(channel_id);
+                }
             }
         }
     }
 }
 
+/// Counts a vote to change a setting in a channel. Settings can be changed freely while nothing is
+/// playing, since nobody is listening. Returns the message to respond with if the setting
+/// shouldn't change yet.
+fn vote_for_setting(
+    guild_model: &mut GuildModel<Song>,
+    delegate: &ModelDelegate,
+    vote_type: VoteType,
+    channel_id: ChannelId,
+    user_id: UserId,
+    more_votes_needed: fn(ChannelId, usize) -> ResponseMessage,
+    already_voted: fn(ChannelId) -> ResponseMessage,
+) -> Option<Message> {
+    match guild_model.vote_for_skip(delegate, vote_type, channel_id, user_id) {
+        VoteStatus::Success | VoteStatus::NothingPlaying => None,
+        VoteStatus::AlreadyVoted => {
+            log::trace!("User has already voted for {:?}, setting will not change", vote_type);
+            Some(Message::Response(already_voted(channel_id)))
+        }
+        VoteStatus::NeedsMoreVotes(count) => {
+            log::trace!("Vote for {:?} has been counted but more are needed, setting will not change", vote_type);
+            Some(Message::Response(more_votes_needed(channel_id, count)))
+        }
+    }
+}
+
+fn queue_limit_message(limit: QueueLimit, song_metadata: SongMetadata) -> Message {
+    Message::Response(match limit {
+        QueueLimit::UserEntries(limit) => ResponseMessage::QueueLengthLimitError { limit },
+        QueueLimit::UserDuration(limit) => ResponseMessage::QueueDurationLimitError {
+            song_title: song_metadata.title,
+            song_url: song_metadata.url,
+            limit,
+        },
+        QueueLimit::EntryDuration(limit) => ResponseMessage::SongDurationLimitError {
+            song_title: song_metadata.title,
+            song_url: song_metadata.url,
+            limit,
+        },
+    })
+}
+
+fn get_integer_option(options: &[application_command::ApplicationCommandInteractionDataOption], name: &str) -> Option<i64> {
+    match options.iter().find(|option| option.name == name).and_then(|option| option.resolved.as_ref()) {
+        Some(application_command::ApplicationCommandInteractionDataOptionValue::Integer(val)) => Some(*val),
+        _ => None,
+    }
+}
+
+fn get_string_option(options: &[application_command::ApplicationCommandInteractionDataOption], name: &str) -> Option<String> {
+    match options.iter().find(|option| option.name == name).and_then(|option| option.resolved.as_ref()) {
+        Some(application_command::ApplicationCommandInteractionDataOptionValue::String(val)) => Some(val.clone()),
+        _ => None,
+    }
+}
+
+/// Gets the volume multiplier songs should play at in a channel.
+fn channel_volume(guild_model: &GuildModel<Song>, channel_id: ChannelId) -> f32 {
+    guild_model.channel_volume(channel_id).unwrap_or(100) as f32 / 100.
+}
+
+/// Parses a timestamp as seconds, `minutes:seconds` or `hours:minutes:seconds`.
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let parts = timestamp
+        .trim()
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let total_secs = match parts.as_slice() {
+        [secs] => *secs,
+        [minutes, secs] if *secs < 60 => minutes.checked_mul(60)?.checked_add(*secs)?,
+        [hours, minutes, secs] if *minutes < 60 && *secs < 60 => hours.checked_mul(3600)?.checked_add(minutes * 60 + secs)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(total_secs))
+}
+
+/// Converts a 1-based queue position as shown to users into a queue index.
+fn position_to_index(position: i64) -> Option<usize> {
+    if position >= 1 {
+        Some(position as usize - 1)
+    } else {
+        None
+    }
+}
+
 struct EndedDelegate {
     frontend: Arc<Frontend>,
     ctx: Context,
@@ -709,3 +2090,25 @@ impl EndedHandler for EndedDelegate {
         tokio::task::spawn(self.frontend.handle_playback_ended(self.ctx, self.guild_id, self.channel_id, ended_handle));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("45"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_timestamp("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp(" 1:23 "), Some(Duration::from_secs(83)));
+        assert_eq!(parse_timestamp("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_timestamp("120:00"), Some(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for timestamp in ["", "abc", "1:60", "1:60:00", "1::2", "-1", "1:2:3:4", "1.5"] {
+            assert_eq!(parse_timestamp(timestamp), None, "{}", timestamp);
+        }
+        assert_eq!(parse_timestamp(&format!("{}:00", u64::MAX)), None);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::config::Config;
+use mrvn_model::{AppModelConfig, QueueMode, SchedulingPolicyKind, VoteThreshold};
+use serde::{Serialize, Deserialize, Deserializer};
+use serenity::model::prelude::GuildId;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// The settings that can be changed with `/config`, in the order they're listed.
+pub const GUILD_CONFIG_KEYS: &[&str] = &[
+    "embed_color",
+    "skip_votes_required",
+    "stop_votes_required",
+    "dj_role_ids",
+    "scheduling_policy",
+    "queue_mode",
+    "history_length",
+    "max_queue_length",
+    "max_queue_duration_secs",
+    "max_song_duration_secs",
+    "volume_requires_dj",
+    "disconnect_min_inactive_secs",
+    "only_disconnect_when_alone",
+];
+
+/// Settings overridden for a single guild. Anything not set falls back to the global config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuildConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "hex_color")]
+    pub embed_color: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_votes_required: Option<VoteThreshold>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_votes_required: Option<VoteThreshold>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dj_role_ids: Option<Vec<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduling_policy: Option<SchedulingPolicyKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_mode: Option<QueueMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_length: Option<usize>,
+
+    // Limits can be overridden to `null`, which removes a limit set in the global config.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "override_option")]
+    pub max_queue_length: Option<Option<usize>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "override_option")]
+    pub max_queue_duration_secs: Option<Option<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "override_option")]
+    pub max_song_duration_secs: Option<Option<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_requires_dj: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disconnect_min_inactive_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_disconnect_when_alone: Option<bool>,
+}
+
+impl GuildConfig {
+    /// Fills in every setting that isn't overridden from the global config.
+    pub fn resolve(&self, config: &Config, guild_id: GuildId) -> GuildConfig {
+        let model_config = self.model_config(config);
+        GuildConfig {
+            embed_color: Some(self.embed_color(config)),
+            skip_votes_required: Some(model_config.skip_votes_required),
+            stop_votes_required: Some(model_config.stop_votes_required),
+            dj_role_ids: Some(self.dj_role_ids(config, guild_id)),
+            scheduling_policy: Some(model_config.scheduling_policy),
+            queue_mode: Some(model_config.queue_mode),
+            history_length: Some(model_config.history_length),
+            max_queue_length: Some(model_config.max_user_entries),
+            max_queue_duration_secs: Some(model_config.max_user_duration.map(|duration| duration.as_secs())),
+            max_song_duration_secs: Some(model_config.max_entry_duration.map(|duration| duration.as_secs())),
+            volume_requires_dj: Some(self.volume_requires_dj(config)),
+            disconnect_min_inactive_secs: Some(self.disconnect_min_inactive_secs(config)),
+            only_disconnect_when_alone: Some(self.only_disconnect_when_alone(config)),
+        }
+    }
+
+    pub fn model_config(&self, config: &Config) -> AppModelConfig {
+        let base_config = config.model_config();
+        AppModelConfig {
+            skip_votes_required: self.skip_votes_required.unwrap_or(base_config.skip_votes_required),
+            stop_votes_required: self.stop_votes_required.unwrap_or(base_config.stop_votes_required),
+            scheduling_policy: self.scheduling_policy.unwrap_or(base_config.scheduling_policy),
+            queue_mode: self.queue_mode.unwrap_or(base_config.queue_mode),
+            history_length: self.history_length.unwrap_or(base_config.history_length),
+            max_user_entries: self.max_queue_length.unwrap_or(base_config.max_user_entries),
+            max_user_duration: self.max_queue_duration_secs
+                .map(|secs| secs.map(Duration::from_secs))
+                .unwrap_or(base_config.max_user_duration),
+            max_entry_duration: self.max_song_duration_secs
+                .map(|secs| secs.map(Duration::from_secs))
+                .unwrap_or(base_config.max_entry_duration),
+        }
+    }
+
+    pub fn embed_color(&self, config: &Config) -> u32 {
+        self.embed_color.unwrap_or(config.embed_color)
+    }
+
+    pub fn dj_role_ids(&self, config: &Config, guild_id: GuildId) -> Vec<u64> {
+        match &self.dj_role_ids {
+            Some(dj_role_ids) => dj_role_ids.clone(),
+            None => config.dj_role_ids.get(&guild_id.0).cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn volume_requires_dj(&self, config: &Config) -> bool {
+        self.volume_requires_dj.unwrap_or(config.volume_requires_dj)
+    }
+
+    pub fn disconnect_min_inactive_secs(&self, config: &Config) -> u64 {
+        self.disconnect_min_inactive_secs.unwrap_or(config.disconnect_min_inactive_secs)
+    }
+
+    pub fn only_disconnect_when_alone(&self, config: &Config) -> bool {
+        self.only_disconnect_when_alone.unwrap_or(config.only_disconnect_when_alone)
+    }
+
+    /// Returns a copy with one setting changed, or reset to the global config if `value` is
+    /// `None`. Values are parsed as JSON, or as a plain string if they aren't valid JSON.
+    pub fn with_value(&self, key: &str, value: Option<&str>) -> Option<GuildConfig> {
+        if !GUILD_CONFIG_KEYS.contains(&key) {
+            return None;
+        }
+
+        let mut values = match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(values)) => values,
+            _ => return None,
+        };
+        match value {
+            Some(value) => {
+                let json_value = serde_json::from_str(value)
+                    .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+                values.insert(key.to_string(), json_value);
+            }
+            None => {
+                values.remove(key);
+            }
+        }
+        serde_json::from_value(serde_json::Value::Object(values)).ok()
+    }
+
+    /// Formats each setting for display, along with whether it's overridden.
+    pub fn describe(&self, config: &Config, guild_id: GuildId) -> Vec<(&'static str, String, bool)> {
+        let overridden_values = serde_json::to_value(self).unwrap_or_default();
+        let resolved_values = serde_json::to_value(self.resolve(config, guild_id)).unwrap_or_default();
+        GUILD_CONFIG_KEYS
+            .iter()
+            .map(|key| {
+                let value_string = resolved_values
+                    .get(*key)
+                    .filter(|value| !value.is_null())
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|| "none".to_string());
+                (*key, value_string, overridden_values.get(*key).is_some())
+            })
+            .collect()
+    }
+}
+
+/// Reads a setting that can be overridden to `null`. Without this, `null` would be read the same
+/// as the setting not being overridden at all.
+fn override_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Keeps each guild's overrides, and saves them to a file whenever they change.
+pub struct GuildConfigStore {
+    path: Option<PathBuf>,
+    guilds: RwLock<HashMap<GuildId, GuildConfig>>,
+
+    /// Held from changing the overrides until they're written, so writes can't overlap and an
+    /// older copy can't replace a newer one.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl GuildConfigStore {
+    pub fn load(path: Option<PathBuf>) -> Result<GuildConfigStore, crate::error::Error> {
+        let guilds = match &path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(guilds_string) => serde_json::from_str(&guilds_string).map_err(crate::error::Error::Json)?,
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => {
+                    log::info!("No guild config file found at {}, using the global config for all guilds", path.display());
+                    HashMap::new()
+                }
+                Err(why) => return Err(crate::error::Error::Io(why)),
+            },
+            None => HashMap::new(),
+        };
+
+        Ok(GuildConfigStore {
+            path,
+            guilds: RwLock::new(guilds),
+            write_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, guild_id: GuildId) -> GuildConfig {
+        self.guilds
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn guilds(&self) -> Vec<(GuildId, GuildConfig)> {
+        self.guilds
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(guild_id, guild_config)| (*guild_id, guild_config.clone()))
+            .collect()
+    }
+
+    pub async fn set(&self, guild_id: GuildId, guild_config: GuildConfig) -> Result<(), crate::error::Error> {
+        let _write_guard = self.write_lock.lock().await;
+        let guilds_string = {
+            let mut guilds = self.guilds.write().unwrap();
+            guilds.insert(guild_id, guild_config);
+            serde_json::to_string_pretty(&*guilds).map_err(crate::error::Error::Json)?
+        };
+
+        if let Some(path) = &self.path {
+            // Write to a temporary file first so a crash mid-write can't corrupt the old config.
+            let temp_path = path.with_extension("tmp");
+            tokio::fs::write(&temp_path, &guilds_string).await.map_err(crate::error::Error::Io)?;
+            tokio::fs::rename(&temp_path, path).await.map_err(crate::error::Error::Io)?;
+            log::trace!("Saved guild config to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Colors are written as hex strings, the same as `embed_color` in the global config.
+mod hex_color {
+    use serde::{Serialize, Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum HexString {
+        String(String),
+
+        // Hex colors made of only digits, e.g. 123456, look like numbers when parsed as JSON.
+        Number(u64),
+    }
+
+    pub fn serialize<S>(color: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        color.map(|color| format!("{:06X}", color)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error> where D: Deserializer<'de> {
+        let hex_string = match Option::<HexString>::deserialize(deserializer)? {
+            Some(HexString::String(s)) => s,
+            Some(HexString::Number(n)) => n.to_string(),
+            None => return Ok(None),
+        };
+        u32::from_str_radix(&hex_string, 16).map(Some).map_err(D::Error::custom)
+    }
+}
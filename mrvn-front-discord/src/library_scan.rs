@@ -0,0 +1,21 @@
+use crate::frontend::Frontend;
+use futures::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Scans the library's directories when MRVN starts, then again every
+/// `library_scan_interval_secs` to pick up files that were added or changed.
+pub async fn scan_loop(frontend: Arc<Frontend>) -> ! {
+    if !frontend.library.has_directories() {
+        future::pending().await
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(frontend.config.library_scan_interval_secs));
+    loop {
+        interval.tick().await;
+        match frontend.library.scan().await {
+            Ok(track_count) => log::info!("Scanned library, found {} tracks", track_count),
+            Err(why) => log::error!("Error while scanning library: {}", why),
+        }
+    }
+}
@@ -9,8 +9,13 @@ mod commands;
 mod config;
 mod error;
 mod frontend;
+mod guild_config;
+mod library_scan;
 mod message;
 mod model_delegate;
+mod playlist_store;
+mod search_cache;
+mod state_store;
 mod voice_handler;
 
 #[tokio::main]
@@ -33,10 +38,35 @@ async fn main() {
     let config: Arc<config::Config> = Arc::new(serde_json::from_reader(config_file).expect("Unable to read config file"));
 
     let mut backend_brain = mrvn_back_ytdl::Brain::new();
-    let model = mrvn_model::AppModel::new(mrvn_model::AppModelConfig {
-        skip_votes_required: config.skip_votes_required,
-        stop_votes_required: config.stop_votes_required,
-    });
+    let model = mrvn_model::AppModel::new(config.model_config());
+
+    if let Some(guild_config_path) = &config.guild_config_path {
+        log::info!("Loading guild config from {}", guild_config_path);
+    }
+    let guild_configs = guild_config::GuildConfigStore::load(config.guild_config_path.as_ref().map(std::path::PathBuf::from))
+        .expect("Unable to load guild config file");
+    for (guild_id, guild_config) in guild_configs.guilds() {
+        model.set_guild_config(guild_id, guild_config.model_config(&config));
+    }
+
+    if let Some(playlist_file_path) = &config.playlist_file_path {
+        log::info!("Loading playlists from {}", playlist_file_path);
+    }
+    let playlists = playlist_store::PlaylistStore::load(config.playlist_file_path.as_ref().map(std::path::PathBuf::from))
+        .expect("Unable to load playlist file");
+
+    if let Some(library_index_path) = &config.library_index_path {
+        log::info!("Loading library index from {}", library_index_path);
+    }
+    let library = mrvn_back_ytdl::Library::load(
+        config.library_directories.iter().map(std::path::PathBuf::from).collect(),
+        config.library_index_path.as_ref().map(std::path::PathBuf::from),
+    ).expect("Unable to load library index file");
+
+    if let Some(state_file_path) = &config.state_file_path {
+        log::info!("Loading state from {}", state_file_path);
+        state_store::load_state(std::path::Path::new(state_file_path), &model).expect("Unable to load state file");
+    }
 
     log::info!("Starting {} voice clients", config.voice_bots.len());
     let mut voice_clients = future::try_join_all(config
@@ -52,7 +82,7 @@ async fn main() {
                 .register_speaker(&mut backend_brain)
         })).await.expect("Unable to create voice client");
 
-    let frontend = Arc::new(crate::frontend::Frontend::new(config.clone(), backend_brain, model));
+    let frontend = Arc::new(crate::frontend::Frontend::new(config.clone(), guild_configs, playlists, library, backend_brain, model));
     let mut command_client = Client::builder(&config.command_bot.token)
         .application_id(config.command_bot.application_id)
         .event_handler(command_handler::CommandHandler::new(frontend.clone()))
@@ -64,11 +94,15 @@ async fn main() {
     ).await.expect("Unable to register commands");
     log::info!("Finished registering application commands");
 
+    let save_loop_future = state_store::save_loop(frontend.clone()).map(|_| Ok(()));
+    let library_scan_loop_future = library_scan::scan_loop(frontend.clone()).map(|_| Ok(()));
     let cleanup_loop_future = cleanup_loop::cleanup_loop(frontend, command_client.cache_and_http.cache.clone()).map(|_| Ok(()));
 
     futures::try_join!(
         command_client.start(),
         future::try_join_all(voice_clients.iter_mut().map(|client| client.start())),
         cleanup_loop_future,
+        save_loop_future,
+        library_scan_loop_future,
     ).expect("Error while running client");
 }
@@ -0,0 +1,109 @@
+use serenity::builder::{CreateActionRow, CreateInteractionResponse, EditInteractionResponse};
+use serenity::http::Http;
+use serenity::model::interactions::{
+    application_command::ApplicationCommandInteraction,
+    message_component::MessageComponentInteraction,
+    InteractionResponseType,
+};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId};
+
+/// An interaction that messages can be sent in response to.
+#[derive(Clone, Copy)]
+pub enum InteractionRef<'interaction> {
+    Command(&'interaction ApplicationCommandInteraction),
+
+    /// If `replace_message` is set, responses replace the message the component was on instead of
+    /// being sent as a new message.
+    Component {
+        component: &'interaction MessageComponentInteraction,
+        replace_message: bool,
+    },
+}
+
+impl InteractionRef<'_> {
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            InteractionRef::Command(command) => command.guild_id,
+            InteractionRef::Component { component, .. } => component.guild_id,
+        }
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        match self {
+            InteractionRef::Command(command) => command.channel_id,
+            InteractionRef::Component { component, .. } => component.channel_id,
+        }
+    }
+
+    /// The message that the response replaces, if any.
+    pub fn replaced_message_id(&self) -> Option<MessageId> {
+        match self {
+            InteractionRef::Component { component, replace_message: true } => Some(component.message.id),
+            _ => None,
+        }
+    }
+
+    /// Tells Discord a response is coming, so the interaction doesn't time out while it's being
+    /// handled. The response must then be sent with [`InteractionRef::edit_response`].
+    pub async fn defer(&self, http: &Http) -> serenity::Result<()> {
+        match self {
+            InteractionRef::Command(command) => command.create_interaction_response(http, |response| {
+                response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+            }).await,
+            InteractionRef::Component { component, replace_message } => component.create_interaction_response(http, |response| {
+                response.kind(match replace_message {
+                    true => InteractionResponseType::DeferredUpdateMessage,
+                    false => InteractionResponseType::DeferredChannelMessageWithSource,
+                })
+            }).await,
+        }
+    }
+
+    pub async fn respond(&self, http: &Http, description: String, color: u32, action_rows: Vec<CreateActionRow>) -> serenity::Result<()> {
+        match self {
+            InteractionRef::Command(command) => command.create_interaction_response(http, |response| {
+                fill_response(response.kind(InteractionResponseType::ChannelMessageWithSource), description, color, action_rows)
+            }).await,
+            InteractionRef::Component { component, replace_message } => component.create_interaction_response(http, |response| {
+                let kind = match replace_message {
+                    true => InteractionResponseType::UpdateMessage,
+                    false => InteractionResponseType::ChannelMessageWithSource,
+                };
+                fill_response(response.kind(kind), description, color, action_rows)
+            }).await,
+        }
+    }
+
+    pub async fn edit_response(&self, http: &Http, description: String, color: u32, action_rows: Vec<CreateActionRow>) -> serenity::Result<()> {
+        match self {
+            InteractionRef::Command(command) => command.edit_original_interaction_response(http, |response| {
+                fill_edit_response(response, description, color, action_rows)
+            }).await.map(|_| ()),
+            InteractionRef::Component { component, .. } => component.edit_original_interaction_response(http, |response| {
+                fill_edit_response(response, description, color, action_rows)
+            }).await.map(|_| ()),
+        }
+    }
+}
+
+fn fill_response(response: &mut CreateInteractionResponse, description: String, color: u32, action_rows: Vec<CreateActionRow>) -> &mut CreateInteractionResponse {
+    response.interaction_response_data(|data| {
+        data
+            .create_embed(|embed| {
+                embed
+                    .description(description)
+                    .color(color)
+            })
+            .components(|components| components.set_action_rows(action_rows))
+    })
+}
+
+fn fill_edit_response(response: &mut EditInteractionResponse, description: String, color: u32, action_rows: Vec<CreateActionRow>) -> &mut EditInteractionResponse {
+    response
+        .create_embed(|embed| {
+            embed
+                .description(description)
+                .color(color)
+        })
+        .components(|components| components.set_action_rows(action_rows))
+}
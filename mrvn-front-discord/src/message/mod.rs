@@ -1,9 +1,27 @@
+use mrvn_model::{AudioFilter, RepeatMode};
+use serenity::builder::CreateActionRow;
+use serenity::model::interactions::message_component::ButtonStyle;
 use serenity::model::prelude::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+mod interaction;
 mod send_message;
 
+pub use self::interaction::*;
 pub use self::send_message::*;
 
+/// The custom ID of the select menu sent with `/search` results.
+pub const SEARCH_SELECT_MENU_ID: &str = "search";
+
+/// Custom IDs of the buttons on the message sent when a song starts playing.
+pub const PLAYING_PAUSE_BUTTON_ID: &str = "playing.pause";
+pub const PLAYING_SKIP_BUTTON_ID: &str = "playing.skip";
+pub const PLAYING_STOP_BUTTON_ID: &str = "playing.stop";
+pub const PLAYING_QUEUE_BUTTON_ID: &str = "playing.queue";
+
+/// Discord's limit on the length of select menu option labels and descriptions.
+const SELECT_OPTION_TEXT_LIMIT: usize = 100;
+
 #[derive(Debug, Clone)]
 pub enum Message {
     Action(ActionMessage),
@@ -24,6 +42,53 @@ impl Message {
             Message::Response(response) => response.to_string(config),
         }
     }
+
+    /// Components to attach to the message, e.g. a select menu to pick a search result.
+    pub fn action_rows(&self, config: &crate::config::Config) -> Vec<CreateActionRow> {
+        match self {
+            // Paused messages keep the buttons, so the pause button can resume playback.
+            Message::Action(ActionMessage::Playing { .. } | ActionMessage::PlayingResponse { .. })
+            | Message::Response(ResponseMessage::Paused { .. }) => {
+                let buttons = [
+                    (PLAYING_PAUSE_BUTTON_ID, "button.pause", ButtonStyle::Secondary),
+                    (PLAYING_SKIP_BUTTON_ID, "button.skip", ButtonStyle::Primary),
+                    (PLAYING_STOP_BUTTON_ID, "button.stop", ButtonStyle::Danger),
+                    (PLAYING_QUEUE_BUTTON_ID, "button.queue", ButtonStyle::Secondary),
+                ];
+                let mut action_row = CreateActionRow::default();
+                for (custom_id, label_key, style) in buttons {
+                    action_row.create_button(|button| {
+                        button
+                            .custom_id(custom_id)
+                            .label(config.get_raw_message(label_key))
+                            .style(style)
+                    });
+                }
+                vec![action_row]
+            }
+            Message::Response(ResponseMessage::SearchResults { results, .. }) => {
+                let mut action_row = CreateActionRow::default();
+                action_row.create_select_menu(|menu| {
+                    menu
+                        .custom_id(SEARCH_SELECT_MENU_ID)
+                        .placeholder(config.get_raw_message("response.search.placeholder"))
+                        .options(|options| {
+                            for result in results {
+                                options.create_option(|option| {
+                                    option
+                                        .label(truncate(&result.song_title, SELECT_OPTION_TEXT_LIMIT))
+                                        .description(truncate(&result.details(config), SELECT_OPTION_TEXT_LIMIT))
+                                        .value(&result.song_url)
+                                });
+                            }
+                            options
+                        })
+                });
+                vec![action_row]
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 /// Action messages have the possibility of being sent not directly as a response to a command
@@ -51,6 +116,57 @@ pub enum ActionMessage {
     UnknownError,
 }
 
+/// A song shown in a queue listing.
+#[derive(Debug, Clone)]
+pub struct QueuedSong {
+    pub song_title: String,
+    pub song_url: String,
+    pub user_id: UserId,
+}
+
+/// A song shown in the guild's history.
+#[derive(Debug, Clone)]
+pub struct PlayedSong {
+    pub song_title: String,
+    pub song_url: String,
+    pub user_id: UserId,
+    pub voice_channel_id: ChannelId,
+    pub started_time: SystemTime,
+    pub skipped: bool,
+}
+
+/// A song found by `/search`.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub song_title: String,
+    pub song_url: String,
+    pub channel: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+impl SearchResult {
+    /// The channel and duration of the song, e.g. `Rick Astley · 3:33`.
+    fn details(&self, config: &crate::config::Config) -> String {
+        let channel = self.channel.as_deref().unwrap_or_else(|| config.get_raw_message("response.search.unknown_channel"));
+        let duration_string = match self.duration {
+            Some(duration) => format_duration(duration),
+            None => config.get_raw_message("response.search.unknown_duration").to_string(),
+        };
+        config.get_message("response.search.details", &[
+            ("channel", channel),
+            ("duration", &duration_string),
+        ])
+    }
+}
+
+/// A guild setting shown by `/config get`.
+#[derive(Debug, Clone)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub is_overridden: bool,
+}
+
 /// Response messages are always sent directly as a response to a command invocation.
 #[derive(Debug, Clone)]
 pub enum ResponseMessage {
@@ -58,6 +174,12 @@ pub enum ResponseMessage {
         song_title: String,
         song_url: String,
     },
+    QueuedPlaylist {
+        playlist_title: String,
+        playlist_url: String,
+        count: usize,
+        skipped_count: usize,
+    },
     QueuedNoSpeakers {
         song_title: String,
         song_url: String,
@@ -121,7 +243,160 @@ pub enum ResponseMessage {
     },
     AlreadyPlayingError {
         voice_channel_id: ChannelId,
-    }
+    },
+    QueueRemoved {
+        song_title: String,
+        song_url: String,
+        position: usize,
+    },
+    QueueMoved {
+        song_title: String,
+        song_url: String,
+        old_position: usize,
+        new_position: usize,
+    },
+    QueueCleared {
+        count: usize,
+    },
+    QueuePositionError {
+        position: i64,
+    },
+    QueueEmptyError,
+    QueueModeChanged {
+        voice_channel_id: ChannelId,
+        is_shared: bool,
+    },
+    LoopModeChanged {
+        voice_channel_id: ChannelId,
+        mode: RepeatMode,
+    },
+    VolumeChanged {
+        voice_channel_id: ChannelId,
+        volume: u32,
+    },
+    VolumeRangeError {
+        max_volume: u32,
+    },
+    FiltersChanged {
+        voice_channel_id: ChannelId,
+        filters: Vec<AudioFilter>,
+    },
+    FilterMoreVotesNeeded {
+        voice_channel_id: ChannelId,
+        count: usize,
+    },
+    FilterAlreadyVotedError {
+        voice_channel_id: ChannelId,
+    },
+    LoopMoreVotesNeeded {
+        voice_channel_id: ChannelId,
+        count: usize,
+    },
+    LoopAlreadyVotedError {
+        voice_channel_id: ChannelId,
+    },
+    HistoryList {
+        songs: Vec<PlayedSong>,
+        first_position: usize,
+        page: usize,
+        page_count: usize,
+    },
+    HistoryPositionError {
+        position: i64,
+    },
+    SearchResults {
+        term: String,
+        results: Vec<SearchResult>,
+    },
+    QueueLengthLimitError {
+        limit: usize,
+    },
+    QueueDurationLimitError {
+        song_title: String,
+        song_url: String,
+        limit: Duration,
+    },
+    SongDurationLimitError {
+        song_title: String,
+        song_url: String,
+        limit: Duration,
+    },
+    PlaylistSaved {
+        name: String,
+        count: usize,
+    },
+    PlaylistLoaded {
+        name: String,
+        count: usize,
+        skipped_count: usize,
+    },
+    PlaylistList {
+        playlists: Vec<(String, usize)>,
+    },
+    PlaylistDeleted {
+        name: String,
+    },
+    PlaylistNotFoundError {
+        name: String,
+    },
+    ConfigList {
+        entries: Vec<ConfigEntry>,
+    },
+    ConfigChanged {
+        key: String,
+        value: String,
+        is_overridden: bool,
+    },
+    ConfigInvalidError {
+        key: String,
+    },
+    Seeked {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+        position: Duration,
+    },
+    SeekMoreVotesNeeded {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+        count: usize,
+    },
+    SeekAlreadyVotedError {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+    },
+    SeekUnsupportedError {
+        song_title: String,
+        song_url: String,
+    },
+    SeekPastEndError {
+        song_title: String,
+        song_url: String,
+        duration: Duration,
+    },
+    InvalidTimestampError {
+        timestamp: String,
+    },
+    NowPlaying {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+        user_id: UserId,
+        position: Duration,
+        duration: Option<Duration>,
+    },
+    MissingPermissionError,
+    QueueList {
+        playing: Option<QueuedSong>,
+        user_songs: Vec<QueuedSong>,
+        upcoming_songs: Vec<QueuedSong>,
+        first_position: usize,
+        voice_channel_id: Option<ChannelId>,
+        page: usize,
+        page_count: usize,
+    },
 }
 
 impl ActionMessage {
@@ -171,6 +446,20 @@ impl ResponseMessage {
                     ("song_url", song_url),
                 ])
             }
+            ResponseMessage::QueuedPlaylist { playlist_title, playlist_url, count, skipped_count } => {
+                let count_string = count.to_string();
+                let skipped_count_string = skipped_count.to_string();
+                let message_key = match skipped_count {
+                    0 => "response.queued_playlist",
+                    _ => "response.queued_playlist_skipped",
+                };
+                config.get_message(message_key, &[
+                    ("playlist_title", playlist_title),
+                    ("playlist_url", playlist_url),
+                    ("count", &count_string),
+                    ("skipped_count", &skipped_count_string),
+                ])
+            }
             ResponseMessage::QueuedNoSpeakers { song_title, song_url } => {
                 config.get_message("response.queued_no_speakers", &[
                     ("song_title", song_title),
@@ -295,6 +584,473 @@ impl ResponseMessage {
                     ("voice_channel_id", &channel_id_string),
                 ])
             }
+            ResponseMessage::QueueRemoved { song_title, song_url, position } => {
+                let position_string = position.to_string();
+                config.get_message("response.queue_removed", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("position", &position_string),
+                ])
+            }
+            ResponseMessage::QueueMoved { song_title, song_url, old_position, new_position } => {
+                let old_position_string = old_position.to_string();
+                let new_position_string = new_position.to_string();
+                config.get_message("response.queue_moved", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("old_position", &old_position_string),
+                    ("new_position", &new_position_string),
+                ])
+            }
+            ResponseMessage::QueueCleared { count } => {
+                if *count == 1 {
+                    config.get_raw_message("response.queue_cleared.singular").to_string()
+                } else {
+                    let count_string = count.to_string();
+                    config.get_message("response.queue_cleared.plural", &[
+                        ("count", &count_string),
+                    ])
+                }
+            }
+            ResponseMessage::QueuePositionError { position } => {
+                let position_string = position.to_string();
+                config.get_message("response.queue_position_error", &[
+                    ("position", &position_string),
+                ])
+            }
+            ResponseMessage::QueueEmptyError => {
+                config.get_raw_message("response.queue_empty_error").to_string()
+            }
+            ResponseMessage::QueueModeChanged { voice_channel_id, is_shared } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                let message_key = match is_shared {
+                    true => "response.queue_mode_shared",
+                    false => "response.queue_mode_per_user",
+                };
+                config.get_message(message_key, &[
+                    ("voice_channel_id", &channel_id_string),
+                ])
+            }
+            ResponseMessage::LoopModeChanged { voice_channel_id, mode } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                let message_key = match mode {
+                    RepeatMode::Off => "response.loop_off",
+                    RepeatMode::Song => "response.loop_song",
+                    RepeatMode::Queue => "response.loop_queue",
+                };
+                config.get_message(message_key, &[
+                    ("voice_channel_id", &channel_id_string),
+                ])
+            }
+            ResponseMessage::HistoryList { songs, first_position, page, page_count } => {
+                let mut lines = vec![config.get_raw_message("response.history.heading").to_string()];
+                if songs.is_empty() {
+                    lines.push(config.get_raw_message("response.history.empty").to_string());
+                }
+                for (index, song) in songs.iter().enumerate() {
+                    let position_string = (first_position + index).to_string();
+                    let user_id_string = song.user_id.0.to_string();
+                    let channel_id_string = song.voice_channel_id.0.to_string();
+                    let started_timestamp_string = song.started_time
+                        .duration_since(UNIX_EPOCH)
+                        .map(|duration| duration.as_secs())
+                        .unwrap_or(0)
+                        .to_string();
+                    let message_key = match song.skipped {
+                        true => "response.history.skipped_entry",
+                        false => "response.history.entry",
+                    };
+                    lines.push(config.get_message(message_key, &[
+                        ("position", &position_string),
+                        ("song_title", &song.song_title),
+                        ("song_url", &song.song_url),
+                        ("user_id", &user_id_string),
+                        ("voice_channel_id", &channel_id_string),
+                        ("started_timestamp", &started_timestamp_string),
+                    ]));
+                }
+
+                if *page_count > 1 {
+                    let page_string = page.to_string();
+                    let page_count_string = page_count.to_string();
+                    lines.push(config.get_message("response.history.page", &[
+                        ("page", &page_string),
+                        ("page_count", &page_count_string),
+                    ]));
+                }
+
+                lines.join("\n")
+            }
+            ResponseMessage::VolumeChanged { voice_channel_id, volume } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                let volume_string = volume.to_string();
+                config.get_message("response.volume_changed", &[
+                    ("voice_channel_id", &channel_id_string),
+                    ("volume", &volume_string),
+                ])
+            }
+            ResponseMessage::VolumeRangeError { max_volume } => {
+                let max_volume_string = max_volume.to_string();
+                config.get_message("response.volume_range_error", &[
+                    ("max_volume", &max_volume_string),
+                ])
+            }
+            ResponseMessage::FiltersChanged { voice_channel_id, filters } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                if filters.is_empty() {
+                    config.get_message("response.filters_cleared", &[
+                        ("voice_channel_id", &channel_id_string),
+                    ])
+                } else {
+                    let filters_string = filters
+                        .iter()
+                        .map(|filter| match filter {
+                            AudioFilter::BassBoost => "bass boost",
+                            AudioFilter::Speed => "speed",
+                            AudioFilter::Nightcore => "nightcore",
+                            AudioFilter::EightD => "8D",
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    config.get_message("response.filters_changed", &[
+                        ("voice_channel_id", &channel_id_string),
+                        ("filters", &filters_string),
+                    ])
+                }
+            }
+            ResponseMessage::FilterMoreVotesNeeded { voice_channel_id, count } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                if *count == 1 {
+                    config.get_message("response.filter_more_votes_needed.singular", &[
+                        ("voice_channel_id", &channel_id_string),
+                    ])
+                } else {
+                    let count_string = count.to_string();
+                    config.get_message("response.filter_more_votes_needed.plural", &[
+                        ("voice_channel_id", &channel_id_string),
+                        ("count", &count_string),
+                    ])
+                }
+            }
+            ResponseMessage::FilterAlreadyVotedError { voice_channel_id } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                config.get_message("response.filter_already_voted_error", &[
+                    ("voice_channel_id", &channel_id_string),
+                ])
+            }
+            ResponseMessage::LoopMoreVotesNeeded { voice_channel_id, count } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                if *count == 1 {
+                    config.get_message("response.loop_more_votes_needed.singular", &[
+                        ("voice_channel_id", &channel_id_string),
+                    ])
+                } else {
+                    let count_string = count.to_string();
+                    config.get_message("response.loop_more_votes_needed.plural", &[
+                        ("voice_channel_id", &channel_id_string),
+                        ("count", &count_string),
+                    ])
+                }
+            }
+            ResponseMessage::LoopAlreadyVotedError { voice_channel_id } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                config.get_message("response.loop_already_voted_error", &[
+                    ("voice_channel_id", &channel_id_string),
+                ])
+            }
+            ResponseMessage::SearchResults { term, results } => {
+                let mut lines = vec![config.get_message("response.search.heading", &[("term", term)])];
+                for (index, result) in results.iter().enumerate() {
+                    let position_string = (index + 1).to_string();
+                    lines.push(config.get_message("response.search.entry", &[
+                        ("position", &position_string),
+                        ("song_title", &result.song_title),
+                        ("song_url", &result.song_url),
+                        ("details", &result.details(config)),
+                    ]));
+                }
+                lines.join("\n")
+            }
+            ResponseMessage::HistoryPositionError { position } => {
+                let position_string = position.to_string();
+                config.get_message("response.history_position_error", &[
+                    ("position", &position_string),
+                ])
+            }
+            ResponseMessage::QueueLengthLimitError { limit } => {
+                let limit_string = limit.to_string();
+                config.get_message("response.queue_length_limit_error", &[
+                    ("limit", &limit_string),
+                ])
+            }
+            ResponseMessage::QueueDurationLimitError { song_title, song_url, limit } => {
+                let limit_string = format_duration(*limit);
+                config.get_message("response.queue_duration_limit_error", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("limit", &limit_string),
+                ])
+            }
+            ResponseMessage::SongDurationLimitError { song_title, song_url, limit } => {
+                let limit_string = format_duration(*limit);
+                config.get_message("response.song_duration_limit_error", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("limit", &limit_string),
+                ])
+            }
+            ResponseMessage::PlaylistSaved { name, count } => {
+                let count_string = count.to_string();
+                config.get_message("response.playlist_saved", &[
+                    ("name", name),
+                    ("count", &count_string),
+                ])
+            }
+            ResponseMessage::PlaylistLoaded { name, count, skipped_count } => {
+                let count_string = count.to_string();
+                let skipped_count_string = skipped_count.to_string();
+                let message_key = match skipped_count {
+                    0 => "response.playlist_loaded",
+                    _ => "response.playlist_loaded_skipped",
+                };
+                config.get_message(message_key, &[
+                    ("name", name),
+                    ("count", &count_string),
+                    ("skipped_count", &skipped_count_string),
+                ])
+            }
+            ResponseMessage::PlaylistList { playlists } => {
+                let mut lines = vec![config.get_raw_message("response.playlist.heading").to_string()];
+                if playlists.is_empty() {
+                    lines.push(config.get_raw_message("response.playlist.empty").to_string());
+                }
+                for (name, count) in playlists {
+                    let count_string = count.to_string();
+                    lines.push(config.get_message("response.playlist.entry", &[
+                        ("name", name),
+                        ("count", &count_string),
+                    ]));
+                }
+                lines.join("\n")
+            }
+            ResponseMessage::PlaylistDeleted { name } => {
+                config.get_message("response.playlist_deleted", &[
+                    ("name", name),
+                ])
+            }
+            ResponseMessage::PlaylistNotFoundError { name } => {
+                config.get_message("response.playlist_not_found_error", &[
+                    ("name", name),
+                ])
+            }
+            ResponseMessage::ConfigList { entries } => {
+                let mut lines = vec![config.get_raw_message("response.config.heading").to_string()];
+                for entry in entries {
+                    let message_key = match entry.is_overridden {
+                        true => "response.config.overridden_entry",
+                        false => "response.config.entry",
+                    };
+                    lines.push(config.get_message(message_key, &[
+                        ("key", &entry.key),
+                        ("value", &entry.value),
+                    ]));
+                }
+                lines.join("\n")
+            }
+            ResponseMessage::ConfigChanged { key, value, is_overridden } => {
+                let message_key = match is_overridden {
+                    true => "response.config_changed",
+                    false => "response.config_reset",
+                };
+                config.get_message(message_key, &[
+                    ("key", key),
+                    ("value", value),
+                ])
+            }
+            ResponseMessage::ConfigInvalidError { key } => {
+                config.get_message("response.config_invalid_error", &[
+                    ("key", key),
+                ])
+            }
+            ResponseMessage::Seeked { song_title, song_url, voice_channel_id, position } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                let position_string = format_duration(*position);
+                config.get_message("response.seeked", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("voice_channel_id", &channel_id_string),
+                    ("position", &position_string),
+                ])
+            }
+            ResponseMessage::SeekMoreVotesNeeded { song_title, song_url, voice_channel_id, count } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                if *count == 1 {
+                    config.get_message("response.seek_more_votes_needed.singular", &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                    ])
+                } else {
+                    let count_string = count.to_string();
+                    config.get_message("response.seek_more_votes_needed.plural", &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                        ("count", &count_string),
+                    ])
+                }
+            }
+            ResponseMessage::SeekAlreadyVotedError { song_title, song_url, voice_channel_id } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                config.get_message("response.seek_already_voted_error", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("voice_channel_id", &channel_id_string),
+                ])
+            }
+            ResponseMessage::SeekUnsupportedError { song_title, song_url } => {
+                config.get_message("response.seek_unsupported_error", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                ])
+            }
+            ResponseMessage::SeekPastEndError { song_title, song_url, duration } => {
+                let duration_string = format_duration(*duration);
+                config.get_message("response.seek_past_end_error", &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("duration", &duration_string),
+                ])
+            }
+            ResponseMessage::InvalidTimestampError { timestamp } => {
+                config.get_message("response.invalid_timestamp_error", &[
+                    ("timestamp", timestamp),
+                ])
+            }
+            ResponseMessage::NowPlaying { song_title, song_url, voice_channel_id, user_id, position, duration } => {
+                let channel_id_string = voice_channel_id.0.to_string();
+                let user_id_string = user_id.0.to_string();
+                let position_string = format_duration(*position);
+                match duration {
+                    Some(duration) => {
+                        let duration_string = format_duration(*duration);
+                        let progress_bar = format_progress_bar(*position, *duration);
+                        config.get_message("response.now_playing", &[
+                            ("song_title", song_title),
+                            ("song_url", song_url),
+                            ("voice_channel_id", &channel_id_string),
+                            ("user_id", &user_id_string),
+                            ("position", &position_string),
+                            ("duration", &duration_string),
+                            ("progress_bar", &progress_bar),
+                        ])
+                    }
+                    None => config.get_message("response.now_playing_unknown_duration", &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                        ("user_id", &user_id_string),
+                        ("position", &position_string),
+                    ]),
+                }
+            }
+            ResponseMessage::MissingPermissionError => {
+                config.get_raw_message("response.missing_permission_error").to_string()
+            }
+            ResponseMessage::QueueList { playing, user_songs, upcoming_songs, first_position, voice_channel_id, page, page_count } => {
+                let mut lines = Vec::new();
+
+                if let Some(playing) = playing {
+                    let user_id_string = playing.user_id.0.to_string();
+                    lines.push(config.get_message("response.queue.playing", &[
+                        ("song_title", &playing.song_title),
+                        ("song_url", &playing.song_url),
+                        ("user_id", &user_id_string),
+                    ]));
+                }
+
+                lines.push(config.get_raw_message("response.queue.user_heading").to_string());
+                if user_songs.is_empty() {
+                    lines.push(config.get_raw_message("response.queue.user_empty").to_string());
+                }
+                for (index, song) in user_songs.iter().enumerate() {
+                    let position_string = (first_position + index).to_string();
+                    lines.push(config.get_message("response.queue.user_entry", &[
+                        ("position", &position_string),
+                        ("song_title", &song.song_title),
+                        ("song_url", &song.song_url),
+                    ]));
+                }
+
+                if let Some(voice_channel_id) = voice_channel_id {
+                    let channel_id_string = voice_channel_id.0.to_string();
+                    lines.push(config.get_message("response.queue.upcoming_heading", &[
+                        ("voice_channel_id", &channel_id_string),
+                    ]));
+                    if upcoming_songs.is_empty() {
+                        lines.push(config.get_message("response.queue.upcoming_empty", &[
+                            ("voice_channel_id", &channel_id_string),
+                        ]));
+                    }
+                    for (index, song) in upcoming_songs.iter().enumerate() {
+                        let position_string = (first_position + index).to_string();
+                        let user_id_string = song.user_id.0.to_string();
+                        lines.push(config.get_message("response.queue.upcoming_entry", &[
+                            ("position", &position_string),
+                            ("song_title", &song.song_title),
+                            ("song_url", &song.song_url),
+                            ("user_id", &user_id_string),
+                        ]));
+                    }
+                }
+
+                if *page_count > 1 {
+                    let page_string = page.to_string();
+                    let page_count_string = page_count.to_string();
+                    lines.push(config.get_message("response.queue.page", &[
+                        ("page", &page_string),
+                        ("page_count", &page_count_string),
+                    ]));
+                }
+
+                lines.join("\n")
+            }
         }
     }
 }
+
+/// Formats a duration as minutes and seconds, with hours if needed, e.g. `3:07` or `1:02:45`.
+pub fn format_duration(duration: Duration) -> String {
+    let total_secs = duration.as_secs();
+    let (hours, minutes, secs) = (total_secs / 3600, (total_secs / 60) % 60, total_secs % 60);
+    match hours {
+        0 => format!("{}:{:02}", minutes, secs),
+        _ => format!("{}:{:02}:{:02}", hours, minutes, secs),
+    }
+}
+
+/// Shortens text to at most `limit` characters, ending it with an ellipsis if it's cut off.
+pub fn truncate(text: &str, limit: usize) -> String {
+    match text.char_indices().nth(limit) {
+        Some(_) => {
+            let mut truncated: String = text.chars().take(limit - 1).collect();
+            truncated.push('…');
+            truncated
+        }
+        None => text.to_string(),
+    }
+}
+
+/// Draws how far through a song playback is as a line of text, e.g. `▬▬▬▬🔘▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬`.
+fn format_progress_bar(position: Duration, duration: Duration) -> String {
+    const PROGRESS_BAR_LENGTH: usize = 20;
+
+    let progress = match duration.is_zero() {
+        true => 0.,
+        false => (position.as_secs_f64() / duration.as_secs_f64()).clamp(0., 1.),
+    };
+    let marker_index = ((progress * PROGRESS_BAR_LENGTH as f64) as usize).min(PROGRESS_BAR_LENGTH - 1);
+    (0..PROGRESS_BAR_LENGTH)
+        .map(|index| if index == marker_index { "🔘" } else { "▬" })
+        .collect()
+}
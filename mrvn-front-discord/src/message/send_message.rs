@@ -1,11 +1,8 @@
-use serenity::{
-    client::Context,
-    model::interactions::{application_command::ApplicationCommandInteraction, InteractionResponseType}
-};
+use serenity::client::Context;
 use mrvn_model::{GuildModel, GuildActionMessage};
 use mrvn_back_ytdl::Song;
 use futures::prelude::*;
-use crate::message::Message;
+use crate::message::{InteractionRef, Message};
 use crate::config::Config;
 use serenity::model::prelude::ChannelId;
 
@@ -13,13 +10,14 @@ use serenity::model::prelude::ChannelId;
 pub enum SendMessageDestination<'interaction> {
     Channel(ChannelId),
     Interaction {
-        interaction: &'interaction ApplicationCommandInteraction,
+        interaction: InteractionRef<'interaction>,
         is_edit: bool,
     }
 }
 
 pub async fn send_messages(
     config: &Config,
+    embed_color: u32,
     ctx: &Context,
     destination: SendMessageDestination<'_>,
     guild_model: &mut GuildModel<Song>,
@@ -27,7 +25,11 @@ pub async fn send_messages(
 ) -> Result<(), crate::error::Error> {
     let message_channel_id = match destination {
         SendMessageDestination::Channel(channel) => channel,
-        SendMessageDestination::Interaction { interaction, .. } => interaction.channel_id,
+        SendMessageDestination::Interaction { interaction, .. } => interaction.channel_id(),
+    };
+    let maybe_replaced_message_id = match destination {
+        SendMessageDestination::Channel(_) => None,
+        SendMessageDestination::Interaction { interaction, .. } => interaction.replaced_message_id(),
     };
 
     // Action messages are special: we only keep the latest one around. This also means out of
@@ -51,26 +53,12 @@ pub async fn send_messages(
     };
     let first_message_future = async {
         if let (SendMessageDestination::Interaction { interaction, is_edit }, Some(first_message)) = (destination, maybe_first_message) {
+            let description = first_message.to_string(config);
+            let action_rows = first_message.action_rows(config);
             if is_edit {
-                interaction.edit_original_interaction_response(&ctx.http, |response| {
-                    response.create_embed(|embed| {
-                        embed
-                            .description(first_message.to_string(config))
-                            .color(config.embed_color)
-                    })
-                }).await.map_err(crate::error::Error::Serenity)?;
+                interaction.edit_response(&ctx.http, description, embed_color, action_rows).await.map_err(crate::error::Error::Serenity)?;
             } else {
-                interaction.create_interaction_response(&ctx.http, |response| {
-                    response
-                        .kind(InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|data| {
-                            data.create_embed(|embed| {
-                                embed
-                                    .description(first_message.to_string(config))
-                                    .color(config.embed_color)
-                            })
-                        })
-                }).await.map_err(crate::error::Error::Serenity)?;
+                interaction.respond(&ctx.http, description, embed_color, action_rows).await.map_err(crate::error::Error::Serenity)?;
             }
         }
         Ok(())
@@ -80,11 +68,13 @@ pub async fn send_messages(
     // action message, keep track of its ID so we can record it later.
     let remaining_messages_future = future::try_join_all(messages_iter.map(|message| async move {
         let channel_message = message_channel_id.send_message(&ctx.http, |create_message| {
-            create_message.embed(|embed| {
-                embed
-                    .description(message.to_string(config))
-                    .color(config.embed_color)
-            })
+            create_message
+                .embed(|embed| {
+                    embed
+                        .description(message.to_string(config))
+                        .color(embed_color)
+                })
+                .components(|components| components.set_action_rows(message.action_rows(config)))
         }).await.map_err(crate::error::Error::Serenity)?;
 
         if message.is_action() {
@@ -95,11 +85,14 @@ pub async fn send_messages(
     }));
 
     // Delete the guild's latest action message from before this operation, if this operation
-    // sent an action message.
+    // sent an action message. If the response replaced the old action message it's kept, since
+    // that's where the new one is.
     let old_action_message = guild_model.last_action_message();
     let delete_old_action_message_future = async {
         if maybe_last_action_message_index.is_some() {
-            if let Some(old_action_message) = old_action_message {
+            let maybe_old_action_message = old_action_message
+                .filter(|old_action_message| Some(old_action_message.message_id) != maybe_replaced_message_id);
+            if let Some(old_action_message) = maybe_old_action_message {
                 old_action_message
                     .channel_id
                     .delete_message(&ctx.http, old_action_message.message_id)
@@ -115,16 +108,22 @@ pub async fn send_messages(
 
     // Set the guild's last action message to the message we sent, if there was one.
     // If we were expecting an action message but there isn't one collected after sending,
-    // the action message was probably sent as the interaction response. This can't be deleted
-    // later so we record there being no last action message.
+    // the action message was sent as the interaction response. A response that replaced a
+    // message is still that message, but other responses can't be deleted later so we record
+    // there being no last action message.
     if maybe_last_action_message_index.is_some() {
         let maybe_sent_message = remaining_messages
             .iter()
-            .find_map(|maybe_message| maybe_message.as_ref());
-        guild_model.set_last_action_message(maybe_sent_message.map(|sent_message| GuildActionMessage {
-            channel_id: sent_message.channel_id,
-            message_id: sent_message.id,
-        }));
+            .find_map(|maybe_message| maybe_message.as_ref())
+            .map(|sent_message| GuildActionMessage {
+                channel_id: sent_message.channel_id,
+                message_id: sent_message.id,
+            });
+        let maybe_replaced_message = maybe_replaced_message_id.map(|message_id| GuildActionMessage {
+            channel_id: message_channel_id,
+            message_id,
+        });
+        guild_model.set_last_action_message(maybe_sent_message.or(maybe_replaced_message));
     }
 
     Ok(())
@@ -3,16 +3,27 @@ use serenity::{prelude::*, model::prelude::*};
 
 pub struct ModelDelegate {
     guild: Guild,
+    dj_role_ids: Vec<u64>,
+    member: Option<Member>,
 }
 
 impl ModelDelegate {
-    pub async fn new(ctx: &Context, guild_id: GuildId) -> Result<ModelDelegate, crate::error::Error> {
+    pub async fn new(ctx: &Context, guild_id: GuildId, dj_role_ids: Vec<u64>) -> Result<ModelDelegate, crate::error::Error> {
         let guild = ctx.cache.guild(guild_id).await.ok_or(crate::error::Error::UnknownGuild(guild_id))?;
         Ok(ModelDelegate {
             guild,
+            dj_role_ids,
+            member: None,
         })
     }
 
+    /// Uses the member sent with an interaction for that user's roles. The member cache can be
+    /// missing them, e.g. without the guild members intent.
+    pub fn with_member(mut self, member: Option<&Member>) -> ModelDelegate {
+        self.member = member.cloned();
+        self
+    }
+
     pub fn get_user_voice_channel(&self, user_id: UserId) -> Option<ChannelId> {
         self.guild.voice_states.get(&user_id).and_then(|state| state.channel_id)
     }
@@ -22,4 +33,30 @@ impl AppModelDelegate for ModelDelegate {
     fn is_user_in_voice_channel(&self, user_id: UserId, channel_id: ChannelId) -> bool {
         self.get_user_voice_channel(user_id) == Some(channel_id)
     }
+
+    fn count_users_in_voice_channel(&self, channel_id: ChannelId) -> usize {
+        self.guild.voice_states
+            .values()
+            .filter(|state| state.channel_id == Some(channel_id))
+            .filter(|state| {
+                let is_bot = state.member
+                    .as_ref()
+                    .or_else(|| self.guild.members.get(&state.user_id))
+                    .map(|member| member.user.bot)
+                    .unwrap_or(false);
+                !is_bot
+            })
+            .count()
+    }
+
+    fn is_user_privileged(&self, user_id: UserId) -> bool {
+        let maybe_member = self.member
+            .as_ref()
+            .filter(|member| member.user.id == user_id)
+            .or_else(|| self.guild.members.get(&user_id));
+        match maybe_member {
+            Some(member) => member.roles.iter().any(|role_id| self.dj_role_ids.contains(&role_id.0)),
+            None => false,
+        }
+    }
 }
@@ -0,0 +1,123 @@
+use serde::{Serialize, Deserialize};
+use serenity::model::prelude::UserId;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedSong {
+    pub title: String,
+    pub url: String,
+    #[serde(default)]
+    pub duration: Option<Duration>,
+}
+
+/// Keeps each user's saved playlists by name, and saves them to a file whenever they change.
+pub struct PlaylistStore {
+    path: Option<PathBuf>,
+    users: RwLock<HashMap<UserId, BTreeMap<String, Vec<SavedSong>>>>,
+
+    /// Held from changing the playlists until they're written, so writes can't overlap and an
+    /// older copy can't replace a newer one.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl PlaylistStore {
+    pub fn load(path: Option<PathBuf>) -> Result<PlaylistStore, crate::error::Error> {
+        let users = match &path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(users_string) => serde_json::from_str(&users_string).map_err(crate::error::Error::Json)?,
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => {
+                    log::info!("No playlist file found at {}, starting with no playlists", path.display());
+                    HashMap::new()
+                }
+                Err(why) => return Err(crate::error::Error::Io(why)),
+            },
+            None => HashMap::new(),
+        };
+
+        Ok(PlaylistStore {
+            path,
+            users: RwLock::new(users),
+            write_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, user_id: UserId, name: &str) -> Option<Vec<SavedSong>> {
+        self.users
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .and_then(|playlists| playlists.get(name))
+            .cloned()
+    }
+
+    /// Lists every song in the user's playlists, in playlist name order.
+    pub fn songs(&self, user_id: UserId) -> Vec<SavedSong> {
+        self.users
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .map(|playlists| playlists.values().flatten().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Lists the user's playlists in name order, along with how many songs are in each.
+    pub fn list(&self, user_id: UserId) -> Vec<(String, usize)> {
+        self.users
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .map(|playlists| {
+                playlists
+                    .iter()
+                    .map(|(name, songs)| (name.clone(), songs.len()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Saves a playlist, replacing any existing playlist with the same name.
+    pub async fn save(&self, user_id: UserId, name: &str, songs: Vec<SavedSong>) -> Result<(), crate::error::Error> {
+        let _write_guard = self.write_lock.lock().await;
+        let users_string = {
+            let mut users = self.users.write().unwrap();
+            users.entry(user_id).or_default().insert(name.to_string(), songs);
+            serde_json::to_string(&*users).map_err(crate::error::Error::Json)?
+        };
+        self.write(users_string).await
+    }
+
+    /// Returns false if the user doesn't have a playlist with the name.
+    pub async fn delete(&self, user_id: UserId, name: &str) -> Result<bool, crate::error::Error> {
+        let _write_guard = self.write_lock.lock().await;
+        let users_string = {
+            let mut users = self.users.write().unwrap();
+            let playlists = match users.get_mut(&user_id) {
+                Some(playlists) => playlists,
+                None => return Ok(false),
+            };
+            if playlists.remove(name).is_none() {
+                return Ok(false);
+            }
+            if playlists.is_empty() {
+                users.remove(&user_id);
+            }
+            serde_json::to_string(&*users).map_err(crate::error::Error::Json)?
+        };
+        self.write(users_string).await?;
+        Ok(true)
+    }
+
+    async fn write(&self, users_string: String) -> Result<(), crate::error::Error> {
+        if let Some(path) = &self.path {
+            // Write to a temporary file first so a crash mid-write can't corrupt the old playlists.
+            let temp_path = path.with_extension("tmp");
+            tokio::fs::write(&temp_path, &users_string).await.map_err(crate::error::Error::Io)?;
+            tokio::fs::rename(&temp_path, path).await.map_err(crate::error::Error::Io)?;
+            log::trace!("Saved playlists to {}", path.display());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use serenity::model::prelude::GuildId;
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+/// How many `/search` results are remembered for each guild.
+const MAX_GUILD_RESULTS: usize = 200;
+
+pub struct CachedResult {
+    pub title: String,
+    pub url: String,
+}
+
+/// Remembers each guild's recent `/search` results, so they can be suggested while typing
+/// without searching online again. Results aren't saved, so they're forgotten on restart.
+#[derive(Default)]
+pub struct SearchCache {
+    guilds: RwLock<HashMap<GuildId, VecDeque<CachedResult>>>,
+}
+
+impl SearchCache {
+    /// Adds results to the front of the guild's cache, replacing older copies of the same songs
+    /// and forgetting the oldest results once the cache is full.
+    pub fn insert(&self, guild_id: GuildId, results: Vec<CachedResult>) {
+        let mut guilds = self.guilds.write().unwrap();
+        let guild_results = guilds.entry(guild_id).or_default();
+        for result in results.into_iter().rev() {
+            guild_results.retain(|cached_result| cached_result.url != result.url);
+            guild_results.push_front(result);
+        }
+        guild_results.truncate(MAX_GUILD_RESULTS);
+    }
+
+    /// Lists the guild's cached results with titles containing a lower case term, most recent
+    /// first, as (title, url) pairs.
+    pub fn find(&self, guild_id: GuildId, lower_term: &str) -> Vec<(String, String)> {
+        self.guilds
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .map(|guild_results| {
+                guild_results
+                    .iter()
+                    .filter(|result| result.title.to_lowercase().contains(lower_term))
+                    .map(|result| (result.title.clone(), result.url.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,138 @@
+use crate::frontend::Frontend;
+use mrvn_back_ytdl::Song;
+use mrvn_model::{AppModel, GuildModelSnapshot};
+use serde::{Serialize, Deserialize};
+use serenity::model::prelude::GuildId;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use futures::prelude::*;
+
+/// Bump this when the state format changes in a way older state files can't be read, and
+/// migrate files from the previous version in [`load_state`].
+const STATE_VERSION: u32 = 2;
+
+#[derive(Deserialize)]
+struct StateFileHeader {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    guilds: Vec<GuildState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GuildState {
+    guild_id: GuildId,
+    model: GuildModelSnapshot<Song>,
+}
+
+pub fn load_state(path: &Path, model: &AppModel<Song>) -> Result<(), crate::error::Error> {
+    let state_string = match std::fs::read_to_string(path) {
+        Ok(state_string) => state_string,
+        Err(why) if why.kind() == std::io::ErrorKind::NotFound => {
+            log::info!("No state file found at {}, starting fresh", path.display());
+            return Ok(());
+        }
+        Err(why) => return Err(crate::error::Error::Io(why)),
+    };
+
+    let header: StateFileHeader = serde_json::from_str(&state_string).map_err(crate::error::Error::Json)?;
+    let state: StateFile = match header.version {
+        STATE_VERSION => serde_json::from_str(&state_string).map_err(crate::error::Error::Json)?,
+        1 => {
+            log::info!("Upgrading state file from version 1");
+            let mut state_value = serde_json::from_str(&state_string).map_err(crate::error::Error::Json)?;
+            add_queue_sequences(&mut state_value);
+            serde_json::from_value(state_value).map_err(crate::error::Error::Json)?
+        }
+
+        // Refuse to start rather than overwrite a file from a newer version with an empty state.
+        version => return Err(crate::error::Error::UnsupportedStateVersion(version)),
+    };
+
+    log::info!("Restoring state for {} guilds", state.guilds.len());
+    for guild_state in state.guilds {
+        model.restore(guild_state.guild_id, guild_state.model);
+    }
+    Ok(())
+}
+
+/// Version 1 files don't record the order songs were queued in. Songs are given an order that
+/// alternates between users' queues, which is the order version 1 played them in.
+fn add_queue_sequences(state_value: &mut serde_json::Value) {
+    let guilds = match state_value["guilds"].as_array_mut() {
+        Some(guilds) => guilds,
+        None => return,
+    };
+    for guild in guilds {
+        let queues = match guild["model"]["queues"].as_array_mut() {
+            Some(queues) => queues,
+            None => continue,
+        };
+        let queue_count = queues.len() as u64;
+        for (queue_index, queue) in queues.iter_mut().enumerate() {
+            let entries = match queue["entries"].as_array_mut() {
+                Some(entries) => entries,
+                None => continue,
+            };
+            for (entry_index, entry) in entries.iter_mut().enumerate() {
+                *entry = serde_json::json!({
+                    "sequence": entry_index as u64 * queue_count + queue_index as u64,
+                    "entry": entry.take(),
+                });
+            }
+        }
+    }
+}
+
+async fn save_state(frontend: &Frontend, path: &Path, last_state_string: &mut String) -> Result<(), crate::error::Error> {
+    let guild_states = future::join_all(frontend.model
+        .guilds()
+        .into_iter()
+        .map(|(guild_id, guild_model_handle)| async move {
+            let guild_model = guild_model_handle.lock().await;
+            GuildState {
+                guild_id,
+                model: guild_model.snapshot(),
+            }
+        }))
+        .await;
+
+    let state_string = serde_json::to_string(&StateFile {
+        version: STATE_VERSION,
+        guilds: guild_states,
+    }).map_err(crate::error::Error::Json)?;
+
+    // Only touch the file if something has changed since it was last written.
+    if state_string == *last_state_string {
+        return Ok(());
+    }
+
+    // Write to a temporary file first so a crash mid-write can't corrupt the old state.
+    let temp_path = path.with_extension("tmp");
+    tokio::fs::write(&temp_path, &state_string).await.map_err(crate::error::Error::Io)?;
+    tokio::fs::rename(&temp_path, path).await.map_err(crate::error::Error::Io)?;
+    *last_state_string = state_string;
+
+    log::trace!("Saved state to {}", path.display());
+    Ok(())
+}
+
+pub async fn save_loop(frontend: Arc<Frontend>) -> ! {
+    let path = match &frontend.config.state_file_path {
+        Some(path) => Path::new(path),
+        None => future::pending().await,
+    };
+
+    let mut last_state_string = String::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(frontend.config.state_save_interval_secs));
+    loop {
+        interval.tick().await;
+        if let Err(why) = save_state(&frontend, path, &mut last_state_string).await {
+            log::error!("Error while saving state: {}", why);
+        }
+    }
+}
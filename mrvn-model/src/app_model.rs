@@ -1,11 +1,12 @@
 use serenity::model::prelude::*;
 use dashmap::DashMap;
-use crate::{AppModelConfig, GuildModel};
+use crate::{AppModelConfig, GuildModel, GuildModelSnapshot};
 use tokio::sync::Mutex;
 use std::sync::Arc;
 
 pub struct AppModel<QueueEntry> {
     config: AppModelConfig,
+    guild_configs: DashMap<GuildId, AppModelConfig>,
     guilds: DashMap<GuildId, Arc<Mutex<GuildModel<QueueEntry>>>>,
 }
 
@@ -13,13 +14,43 @@ impl<QueueEntry> AppModel< QueueEntry> {
     pub fn new(config: AppModelConfig) -> Self {
         AppModel {
             config,
+            guild_configs: DashMap::new(),
             guilds: DashMap::new(),
         }
     }
 
     pub fn get(&self, guild_id: GuildId) -> Arc<Mutex<GuildModel<QueueEntry>>> {
         let handle = self.guilds.entry(guild_id)
-            .or_insert_with(|| Arc::new(Mutex::new(GuildModel::new(self.config))));
+            .or_insert_with(|| Arc::new(Mutex::new(GuildModel::new(self.guild_config(guild_id)))));
         handle.clone()
     }
+
+    /// Gets a guild's model if it has one, without creating it.
+    pub fn find(&self, guild_id: GuildId) -> Option<Arc<Mutex<GuildModel<QueueEntry>>>> {
+        self.guilds.get(&guild_id).map(|handle| handle.clone())
+    }
+
+    pub fn restore(&self, guild_id: GuildId, snapshot: GuildModelSnapshot<QueueEntry>) {
+        self.guilds.insert(guild_id, Arc::new(Mutex::new(GuildModel::from_snapshot(self.guild_config(guild_id), snapshot))));
+    }
+
+    pub fn guild_config(&self, guild_id: GuildId) -> AppModelConfig {
+        self.guild_configs
+            .get(&guild_id)
+            .map(|config| *config)
+            .unwrap_or(self.config)
+    }
+
+    /// Sets the config used by guild models created from now on. Guild models that already
+    /// exist must be updated with [`GuildModel::set_config`].
+    pub fn set_guild_config(&self, guild_id: GuildId, config: AppModelConfig) {
+        self.guild_configs.insert(guild_id, config);
+    }
+
+    pub fn guilds(&self) -> Vec<(GuildId, Arc<Mutex<GuildModel<QueueEntry>>>)> {
+        self.guilds
+            .iter()
+            .map(|guild| (*guild.key(), guild.value().clone()))
+            .collect()
+    }
 }
@@ -2,4 +2,10 @@ use serenity::model::prelude::*;
 
 pub trait AppModelDelegate {
     fn is_user_in_voice_channel(&self, user_id: UserId, channel_id: ChannelId) -> bool;
+
+    /// Counts the people in a voice channel, not including bots.
+    fn count_users_in_voice_channel(&self, channel_id: ChannelId) -> usize;
+
+    /// Whether the user can skip and stop without needing votes from anybody else.
+    fn is_user_privileged(&self, user_id: UserId) -> bool;
 }
@@ -0,0 +1,12 @@
+use serde::{Serialize, Deserialize};
+
+/// An effect applied to everything played in a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFilter {
+    BassBoost,
+    Speed,
+    Nightcore,
+    #[serde(rename = "8d")]
+    EightD,
+}
@@ -1,5 +1,105 @@
+use crate::SchedulingPolicyKind;
+use serde::{Serialize, Deserialize};
+use std::time::Duration;
+
 #[derive(Debug, Clone, Copy)]
 pub struct AppModelConfig {
-    pub skip_votes_required: usize,
-    pub stop_votes_required: usize,
+    pub skip_votes_required: VoteThreshold,
+    pub stop_votes_required: VoteThreshold,
+    pub scheduling_policy: SchedulingPolicyKind,
+    pub queue_mode: QueueMode,
+
+    /// How many played entries are remembered in each guild.
+    pub history_length: usize,
+
+    /// The most entries each user can have queued at once.
+    pub max_user_entries: Option<usize>,
+
+    /// The longest each user's queued entries can add up to.
+    pub max_user_duration: Option<Duration>,
+
+    /// The longest a single entry can be.
+    pub max_entry_duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueMode {
+    /// Each user has their own queue that follows them between channels.
+    #[default]
+    PerUser,
+
+    /// Songs queued in a channel go into a queue shared by everyone in it, which plays before
+    /// anybody's own queue.
+    Shared,
+}
+
+/// How many votes are needed to skip or stop, either as a fixed count or relative to the number
+/// of people listening in the channel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VoteThreshold {
+    Count(usize),
+    Ratio {
+        ratio: f64,
+        #[serde(default)]
+        min: Option<usize>,
+        #[serde(default)]
+        max: Option<usize>,
+    },
+}
+
+impl VoteThreshold {
+    pub fn votes_required(self, listener_count: usize) -> usize {
+        match self {
+            VoteThreshold::Count(count) => count,
+            VoteThreshold::Ratio { ratio, min, max } => {
+                let mut votes_required = (ratio * listener_count as f64).ceil() as usize;
+                if let Some(max) = max {
+                    votes_required = votes_required.min(max);
+                }
+                if let Some(min) = min {
+                    votes_required = votes_required.max(min);
+                }
+
+                // At least one vote is always needed, even in an empty channel
+                votes_required.max(1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_ignore_how_many_people_are_listening() {
+        assert_eq!(VoteThreshold::Count(3).votes_required(1), 3);
+        assert_eq!(VoteThreshold::Count(3).votes_required(10), 3);
+    }
+
+    #[test]
+    fn ratios_round_up() {
+        let threshold = VoteThreshold::Ratio { ratio: 0.5, min: None, max: None };
+        assert_eq!(threshold.votes_required(4), 2);
+        assert_eq!(threshold.votes_required(5), 3);
+    }
+
+    #[test]
+    fn ratios_are_kept_between_min_and_max() {
+        let threshold = VoteThreshold::Ratio { ratio: 0.5, min: Some(2), max: Some(4) };
+        assert_eq!(threshold.votes_required(1), 2);
+        assert_eq!(threshold.votes_required(6), 3);
+        assert_eq!(threshold.votes_required(20), 4);
+    }
+
+    #[test]
+    fn ratios_always_need_one_vote() {
+        let threshold = VoteThreshold::Ratio { ratio: 0.5, min: None, max: None };
+        assert_eq!(threshold.votes_required(0), 1);
+
+        let threshold = VoteThreshold::Ratio { ratio: 0.5, min: Some(0), max: Some(0) };
+        assert_eq!(threshold.votes_required(10), 1);
+    }
 }
@@ -0,0 +1,8 @@
+use std::time::Duration;
+
+/// Lets the model enforce duration limits on queue entries.
+pub trait EntryDuration {
+    /// How long the entry plays for, if it's known. Entries without a duration (e.g. live
+    /// streams) aren't affected by duration limits.
+    fn duration(&self) -> Option<Duration>;
+}
@@ -1,16 +1,45 @@
 use serenity::model::prelude::*;
-use std::collections::{VecDeque, HashMap, HashSet};
-use crate::{AppModelConfig, AppModelDelegate};
+use std::collections::{BTreeSet, VecDeque, HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime};
+use crate::{AppModelConfig, AppModelDelegate, AudioFilter, EntryDuration, QueueMode, SchedulingCandidate, SchedulingPolicy};
+use serde::{Serialize, Deserialize};
 
-fn find_first_user_in_channel<'a, Entry: 'a, Delegate: AppModelDelegate>(mut queues: impl Iterator<Item=&'a Queue<Entry>>, delegate: &Delegate, channel_id: ChannelId) -> Option<UserId> {
-    queues
-        .find(|queue| delegate.is_user_in_voice_channel(queue.user_id, channel_id))
-        .map(|queue| queue.user_id)
+/// Used in place of real airtime when predicting upcoming entries, since they haven't played yet.
+const ESTIMATED_ENTRY_AIRTIME: Duration = Duration::from_secs(210);
+
+fn find_next_user<Delegate: AppModelDelegate>(
+    policy: &dyn SchedulingPolicy,
+    queues: &[(UserId, u64)],
+    airtime: &HashMap<UserId, Duration>,
+    last_user_id: Option<UserId>,
+    delegate: &Delegate,
+    channel_id: ChannelId,
+) -> Option<UserId> {
+    let candidates: Vec<_> = queues
+        .iter()
+        .map(|(user_id, first_queued_sequence)| SchedulingCandidate {
+            user_id: *user_id,
+            is_in_channel: delegate.is_user_in_voice_channel(*user_id, channel_id),
+            first_queued_sequence: *first_queued_sequence,
+            airtime: airtime.get(user_id).copied().unwrap_or_default(),
+        })
+        .collect();
+    policy.next_user(&candidates, last_user_id)
 }
 
+/// What a vote is for. Votes only count towards the exact same change, e.g. votes to seek to
+/// different positions are counted separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VoteType {
     Skip,
     Stop,
+    Seek(Duration),
+
+    /// Turning a filter on or off, or turning all filters off if `None`.
+    Filter(Option<AudioFilter>),
+
+    /// Changing the channel's repeat mode to this one.
+    Loop(RepeatMode),
 }
 
 pub enum VoteStatus {
@@ -26,52 +55,174 @@ pub enum ReplaceStatus<QueueEntry> {
     ReplacedCurrent(ChannelId),
 }
 
+/// A limit that stopped an entry from being queued, along with the configured value.
+pub enum QueueLimit {
+    UserEntries(usize),
+    UserDuration(Duration),
+    EntryDuration(Duration),
+}
+
 pub enum NextEntry<QueueEntry> {
     NoneAvailable,
     AlreadyPlaying,
     Entry(QueueEntry),
 }
 
+pub struct UpcomingEntry<'model, QueueEntry> {
+    pub user_id: UserId,
+    pub entry: &'model QueueEntry,
+}
+
+/// An entry along with when it was queued relative to other entries in the guild.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueuedEntry<QueueEntry> {
+    pub sequence: u64,
+    pub entry: QueueEntry,
+}
+
 struct Queue<Entry> {
     user_id: UserId,
-    entries: VecDeque<Entry>,
+    entries: VecDeque<QueuedEntry<Entry>>,
 }
 
-enum ChannelPlayingState {
+/// An entry in a channel's shared queue, which remembers who queued it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelQueuedEntry<QueueEntry> {
+    pub user_id: UserId,
+    pub sequence: u64,
+    pub entry: QueueEntry,
+}
+
+impl<Entry> Queue<Entry> {
+    fn first_queued_sequence(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.sequence).min().unwrap_or(u64::MAX)
+    }
+}
+
+/// An entry that started playing in the guild, kept so it can be found and queued again later.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlayedEntry<QueueEntry> {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub started_time: SystemTime,
+    pub skipped: bool,
+    pub entry: QueueEntry,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+
+    /// The entry that just finished is played again, unless it was skipped.
+    Song,
+
+    /// The entry that just finished is put back at the end of its owner's queue, unless it was
+    /// skipped.
+    Queue,
+}
+
+enum ChannelPlayingState<Entry> {
     NotPlaying,
     Stopped,
     Playing {
         playing_user_id: UserId,
-        skip_votes: HashSet<UserId>,
-        stop_votes: HashSet<UserId>,
+        entry: Entry,
+        started_time: Instant,
+        skipped: bool,
+        votes: HashMap<VoteType, HashSet<UserId>>,
     }
 }
 
-impl ChannelPlayingState {
+impl<Entry> ChannelPlayingState<Entry> {
     fn is_playing(&self) -> bool {
-        match self {
-            ChannelPlayingState::Playing { .. } => true,
-            _ => false,
-        }
+        matches!(self, ChannelPlayingState::Playing { .. })
     }
 }
 
-struct ChannelModel {
-    playing: ChannelPlayingState,
+struct ChannelModel<Entry> {
+    playing: ChannelPlayingState<Entry>,
+    queue_mode: Option<QueueMode>,
+    repeat_mode: RepeatMode,
+
+    /// Playback volume as a percentage, or `None` to play at the original volume.
+    volume: Option<u32>,
+    filters: BTreeSet<AudioFilter>,
+    entries: VecDeque<ChannelQueuedEntry<Entry>>,
+}
+
+impl<Entry> ChannelModel<Entry> {
+    fn new(playing: ChannelPlayingState<Entry>) -> Self {
+        ChannelModel {
+            playing,
+            queue_mode: None,
+            repeat_mode: RepeatMode::Off,
+            volume: None,
+            filters: BTreeSet::new(),
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn has_saved_state(&self) -> bool {
+        self.queue_mode.is_some() || self.repeat_mode != RepeatMode::Off || self.volume.is_some()
+            || !self.filters.is_empty() || !self.entries.is_empty()
+    }
+
+    /// Channels that aren't playing and have no settings or entries can be forgotten.
+    fn is_empty(&self) -> bool {
+        !self.playing.is_playing() && !self.has_saved_state()
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct GuildActionMessage {
     pub channel_id: ChannelId,
     pub message_id: MessageId,
 }
 
+/// The persistent parts of a guild's state. Playback doesn't survive a restart, so entries that
+/// were playing are saved at the front of the queue they'd be played from.
+#[derive(Serialize, Deserialize)]
+pub struct GuildModelSnapshot<QueueEntry> {
+    pub message_channel: Option<ChannelId>,
+    pub last_action_message: Option<GuildActionMessage>,
+    pub queues: Vec<QueueSnapshot<QueueEntry>>,
+    pub stopped_channels: Vec<ChannelId>,
+    #[serde(default = "Vec::new")]
+    pub channel_queues: Vec<ChannelQueueSnapshot<QueueEntry>>,
+    #[serde(default = "Vec::new")]
+    pub history: Vec<PlayedEntry<QueueEntry>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChannelQueueSnapshot<QueueEntry> {
+    pub channel_id: ChannelId,
+    pub queue_mode: Option<QueueMode>,
+    #[serde(default)]
+    pub repeat_mode: RepeatMode,
+    #[serde(default)]
+    pub volume: Option<u32>,
+    #[serde(default)]
+    pub filters: BTreeSet<AudioFilter>,
+    pub entries: Vec<ChannelQueuedEntry<QueueEntry>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct QueueSnapshot<QueueEntry> {
+    pub user_id: UserId,
+    pub entries: Vec<QueuedEntry<QueueEntry>>,
+}
+
 pub struct GuildModel<QueueEntry> {
     config: AppModelConfig,
     message_channel: Option<ChannelId>,
     last_action_message: Option<GuildActionMessage>,
     queues: Vec<Queue<QueueEntry>>,
-    channels: HashMap<ChannelId, ChannelModel>,
+    channels: HashMap<ChannelId, ChannelModel<QueueEntry>>,
+    next_sequence: u64,
+    airtime: HashMap<UserId, Duration>,
+    history: VecDeque<PlayedEntry<QueueEntry>>,
 }
 
 impl<QueueEntry> GuildModel<QueueEntry> {
@@ -82,6 +233,156 @@ impl<QueueEntry> GuildModel<QueueEntry> {
             last_action_message: None,
             queues: Vec::new(),
             channels: HashMap::new(),
+            next_sequence: 0,
+            airtime: HashMap::new(),
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn from_snapshot(config: AppModelConfig, snapshot: GuildModelSnapshot<QueueEntry>) -> Self {
+        let user_sequences = snapshot.queues
+            .iter()
+            .flat_map(|queue| queue.entries.iter())
+            .map(|entry| entry.sequence);
+        let channel_sequences = snapshot.channel_queues
+            .iter()
+            .flat_map(|channel| channel.entries.iter())
+            .map(|entry| entry.sequence);
+        let next_sequence = user_sequences
+            .chain(channel_sequences)
+            .map(|sequence| sequence + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut channels: HashMap<_, _> = snapshot.stopped_channels
+            .into_iter()
+            .map(|channel_id| (channel_id, ChannelModel::new(ChannelPlayingState::Stopped)))
+            .collect();
+        for channel_queue in snapshot.channel_queues {
+            let channel = channels
+                .entry(channel_queue.channel_id)
+                .or_insert_with(|| ChannelModel::new(ChannelPlayingState::NotPlaying));
+            channel.queue_mode = channel_queue.queue_mode;
+            channel.repeat_mode = channel_queue.repeat_mode;
+            channel.volume = channel_queue.volume;
+            channel.filters = channel_queue.filters;
+            channel.entries = channel_queue.entries.into();
+        }
+
+        // Only keep the most recent history, in case the configured length has been reduced
+        let mut history: VecDeque<_> = snapshot.history.into();
+        while history.len() > config.history_length {
+            history.pop_front();
+        }
+
+        GuildModel {
+            config,
+            message_channel: snapshot.message_channel,
+            last_action_message: snapshot.last_action_message,
+            queues: snapshot.queues
+                .into_iter()
+                .filter(|queue| !queue.entries.is_empty())
+                .map(|queue| Queue {
+                    user_id: queue.user_id,
+                    entries: queue.entries.into(),
+                })
+                .collect(),
+            channels,
+            next_sequence,
+            airtime: HashMap::new(),
+            history,
+        }
+    }
+
+    pub fn snapshot(&self) -> GuildModelSnapshot<QueueEntry> where QueueEntry: Clone {
+        let mut queues: Vec<_> = self.queues
+            .iter()
+            .map(|queue| QueueSnapshot {
+                user_id: queue.user_id,
+                entries: queue.entries.iter().cloned().collect(),
+            })
+            .collect();
+        let mut channel_queues: Vec<_> = self.channels
+            .iter()
+            .filter(|(_, channel)| channel.has_saved_state())
+            .map(|(channel_id, channel)| ChannelQueueSnapshot {
+                channel_id: *channel_id,
+                queue_mode: channel.queue_mode,
+                repeat_mode: channel.repeat_mode,
+                volume: channel.volume,
+                filters: channel.filters.clone(),
+                entries: channel.entries.iter().cloned().collect(),
+            })
+            .collect();
+
+        // Put entries that are playing back at the front of the queue, so they play again after
+        // a restart instead of being lost. Sequence 0 keeps them ahead of other entries.
+        for (channel_id, channel) in &self.channels {
+            let (user_id, entry) = match &channel.playing {
+                ChannelPlayingState::Playing { playing_user_id, entry, skipped: false, .. } => (*playing_user_id, entry.clone()),
+                _ => continue,
+            };
+
+            if self.channel_queue_mode(*channel_id) == QueueMode::Shared {
+                let channel_queue_index = match channel_queues.iter().position(|channel_queue| channel_queue.channel_id == *channel_id) {
+                    Some(index) => index,
+                    None => {
+                        channel_queues.push(ChannelQueueSnapshot {
+                            channel_id: *channel_id,
+                            queue_mode: channel.queue_mode,
+                            repeat_mode: channel.repeat_mode,
+                            volume: channel.volume,
+                            filters: channel.filters.clone(),
+                            entries: Vec::new(),
+                        });
+                        channel_queues.len() - 1
+                    }
+                };
+                channel_queues[channel_queue_index].entries.insert(0, ChannelQueuedEntry {
+                    user_id,
+                    sequence: 0,
+                    entry,
+                });
+            } else {
+                let queue_index = match queues.iter().position(|queue| queue.user_id == user_id) {
+                    Some(index) => index,
+                    None => {
+                        queues.push(QueueSnapshot {
+                            user_id,
+                            entries: Vec::new(),
+                        });
+                        queues.len() - 1
+                    }
+                };
+                queues[queue_index].entries.insert(0, QueuedEntry {
+                    sequence: 0,
+                    entry,
+                });
+            }
+        }
+
+        GuildModelSnapshot {
+            message_channel: self.message_channel,
+            last_action_message: self.last_action_message,
+            queues,
+            stopped_channels: self.channels
+                .iter()
+                .filter(|(_, channel)| matches!(channel.playing, ChannelPlayingState::Stopped))
+                .map(|(channel_id, _)| *channel_id)
+                .collect(),
+            channel_queues,
+            history: self.history.iter().cloned().collect(),
+        }
+    }
+
+    pub fn config(&self) -> AppModelConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: AppModelConfig) {
+        self.config = config;
+        while self.history.len() > config.history_length {
+            self.history.pop_front();
         }
     }
 
@@ -102,86 +403,352 @@ impl<QueueEntry> GuildModel<QueueEntry> {
     }
 
     pub fn is_channel_stopped(&self, channel_id: ChannelId) -> bool {
-        match self.get_channel_playing_state(channel_id) {
-            Some(ChannelPlayingState::Stopped) => true,
-            _ => false,
-        }
+        matches!(self.get_channel_playing_state(channel_id), Some(ChannelPlayingState::Stopped))
     }
 
     pub fn set_channel_stopped(&mut self, channel_id: ChannelId) {
-        self.create_channel(channel_id).playing = ChannelPlayingState::Stopped;
+        self.replace_channel_playing_state(channel_id, ChannelPlayingState::Stopped);
+    }
+
+    pub fn channel_queue_mode(&self, channel_id: ChannelId) -> QueueMode {
+        self.channels
+            .get(&channel_id)
+            .and_then(|channel| channel.queue_mode)
+            .unwrap_or(self.config.queue_mode)
+    }
+
+    /// Overrides the guild's queue mode for a channel, or goes back to the guild's mode if `None`.
+    /// Entries already in the channel's shared queue stay there until they're played.
+    pub fn set_channel_queue_mode(&mut self, channel_id: ChannelId, queue_mode: Option<QueueMode>) {
+        self.create_channel(channel_id).queue_mode = queue_mode;
+        self.channels.retain(|_, channel| !channel.is_empty());
+    }
+
+    pub fn channel_repeat_mode(&self, channel_id: ChannelId) -> RepeatMode {
+        self.channels
+            .get(&channel_id)
+            .map(|channel| channel.repeat_mode)
+            .unwrap_or_default()
+    }
+
+    pub fn set_channel_repeat_mode(&mut self, channel_id: ChannelId, repeat_mode: RepeatMode) {
+        self.create_channel(channel_id).repeat_mode = repeat_mode;
+        self.channels.retain(|_, channel| !channel.is_empty());
+    }
+
+    pub fn channel_volume(&self, channel_id: ChannelId) -> Option<u32> {
+        self.channels
+            .get(&channel_id)
+            .and_then(|channel| channel.volume)
+    }
+
+    /// Sets the volume entries play at in a channel as a percentage, or goes back to the original
+    /// volume if `None`.
+    pub fn set_channel_volume(&mut self, channel_id: ChannelId, volume: Option<u32>) {
+        self.create_channel(channel_id).volume = volume;
+        self.channels.retain(|_, channel| !channel.is_empty());
+    }
+
+    pub fn channel_filters(&self, channel_id: ChannelId) -> BTreeSet<AudioFilter> {
+        self.channels
+            .get(&channel_id)
+            .map(|channel| channel.filters.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_channel_filters(&mut self, channel_id: ChannelId, filters: BTreeSet<AudioFilter>) {
+        self.create_channel(channel_id).filters = filters;
+        self.channels.retain(|_, channel| !channel.is_empty());
     }
 
     // User commands:
-    pub fn push_entry(&mut self, user_id: UserId, entry: QueueEntry) {
-        self.create_user_queue(user_id).entries.push_back(entry);
+    /// Adds an entry to the user's queue, or to the channel's shared queue if the user is in a
+    /// channel that has one.
+    pub fn push_entry(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>, entry: QueueEntry) -> Result<(), QueueLimit> where QueueEntry: EntryDuration {
+        self.check_limits(user_id, &entry, None)?;
+        self.push_entry_unchecked(user_id, maybe_channel_id, entry);
+        Ok(())
     }
 
-    pub fn replace_entry(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>, entry: QueueEntry) -> ReplaceStatus<QueueEntry> {
-        let queue = self.create_user_queue(user_id);
-        let removed_entry = queue.entries.pop_back();
-        queue.entries.push_back(entry);
+    pub fn replace_entry(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>, entry: QueueEntry) -> Result<ReplaceStatus<QueueEntry>, QueueLimit> where QueueEntry: EntryDuration {
+        // The entry being replaced doesn't count towards the limits
+        let maybe_replaced_entry = match self.get_shared_channel(maybe_channel_id) {
+            Some(channel_id) => self.channels
+                .get(&channel_id)
+                .and_then(|channel| channel.entries.iter().rev().find(|entry| entry.user_id == user_id))
+                .map(|channel_entry| &channel_entry.entry),
+            None => self.user_queue(user_id).last(),
+        };
+        self.check_limits(user_id, &entry, maybe_replaced_entry)?;
+
+        let removed_entry = match self.get_shared_channel(maybe_channel_id) {
+            Some(channel_id) => {
+                let QueuedEntry { sequence, entry } = self.sequence_entry(entry);
+                let channel = self.create_channel(channel_id);
+                let removed_entry = channel.entries
+                    .iter()
+                    .rposition(|entry| entry.user_id == user_id)
+                    .and_then(|index| channel.entries.remove(index))
+                    .map(|removed_entry| removed_entry.entry);
+                channel.entries.push_back(ChannelQueuedEntry {
+                    user_id,
+                    sequence,
+                    entry,
+                });
+                removed_entry
+            }
+            None => {
+                let queued_entry = self.sequence_entry(entry);
+                let queue = self.create_user_queue(user_id);
+                let removed_entry = queue.entries.pop_back().map(|removed_entry| removed_entry.entry);
+                queue.entries.push_back(queued_entry);
+                removed_entry
+            }
+        };
 
         match removed_entry {
-            Some(entry) => ReplaceStatus::ReplacedInQueue(entry),
+            Some(removed_entry) => Ok(ReplaceStatus::ReplacedInQueue(removed_entry)),
             None => {
                 // If the current channel is playing this user, the current song should be skipped.
                 if let Some(channel_id) = maybe_channel_id {
-                    let maybe_playing_user = self.get_channel_playing_user(channel_id);
-                    if maybe_playing_user == Some(user_id) {
-                        return ReplaceStatus::ReplacedCurrent(channel_id);
+                    if let Some(ChannelPlayingState::Playing { playing_user_id, skipped, .. }) = self.get_channel_playing_state_mut(channel_id) {
+                        if *playing_user_id == user_id {
+                            *skipped = true;
+                            return Ok(ReplaceStatus::ReplacedCurrent(channel_id));
+                        }
                     }
                 }
 
-                ReplaceStatus::Queued
+                Ok(ReplaceStatus::Queued)
+            }
+        }
+    }
+
+    /// Removes an entry from the user's entries, indexed in the order of
+    /// [`GuildModel::user_entries`].
+    pub fn remove_entry(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>, index: usize) -> Option<QueueEntry> {
+        let channel_indices: Vec<_> = self.get_shared_channel(maybe_channel_id)
+            .and_then(|channel_id| self.channels.get(&channel_id))
+            .map(|channel| {
+                channel.entries
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, channel_entry)| channel_entry.user_id == user_id)
+                    .map(|(channel_index, _)| channel_index)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let removed_entry = match channel_indices.get(index) {
+            Some(channel_index) => {
+                let channel_id = self.get_shared_channel(maybe_channel_id)?;
+                self.channels.get_mut(&channel_id)?.entries.remove(*channel_index).map(|removed_entry| removed_entry.entry)
+            }
+            None => {
+                let queue_index = index - channel_indices.len();
+                self.get_user_queue_mut(user_id)?.entries.remove(queue_index).map(|removed_entry| removed_entry.entry)
+            }
+        };
+        self.queues.retain(|queue| !queue.entries.is_empty());
+        removed_entry
+    }
+
+    /// Moves an entry to a different position in the user's entries, indexed in the order of
+    /// [`GuildModel::user_entries`]. Indices past the end move the entry to the end. Only the
+    /// user's own entries are reordered, so entries in a shared queue don't move ahead of anybody
+    /// else's.
+    pub fn move_entry(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>, from_index: usize, to_index: usize) -> Option<&QueueEntry> where QueueEntry: Clone {
+        let mut entries: Vec<_> = self.user_entries(user_id, maybe_channel_id).cloned().collect();
+        if from_index >= entries.len() {
+            return None;
+        }
+        let entry = entries.remove(from_index);
+        let to_index = to_index.min(entries.len());
+        entries.insert(to_index, entry);
+
+        for (slot, entry) in self.user_entries_mut(user_id, maybe_channel_id).zip(entries) {
+            *slot = entry;
+        }
+        self.user_entries(user_id, maybe_channel_id).nth(to_index)
+    }
+
+    /// Removes all of the user's entries, returning how many were removed.
+    pub fn clear_entries(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>) -> usize {
+        let removed_count = self.user_entries(user_id, maybe_channel_id).count();
+        if let Some(channel) = self.get_shared_channel(maybe_channel_id).and_then(|channel_id| self.channels.get_mut(&channel_id)) {
+            channel.entries.retain(|channel_entry| channel_entry.user_id != user_id);
+        }
+        self.queues.retain(|queue| queue.user_id != user_id);
+        removed_count
+    }
+
+    // Queries:
+    pub fn user_queue(&self, user_id: UserId) -> impl Iterator<Item=&QueueEntry> {
+        self.queues
+            .iter()
+            .find(|queue| queue.user_id == user_id)
+            .into_iter()
+            .flat_map(|queue| queue.entries.iter().map(|entry| &entry.entry))
+    }
+
+    /// The user's entries in the order they will play: their entries in the channel's shared
+    /// queue, if it has one, followed by their own queue.
+    pub fn user_entries(&self, user_id: UserId, maybe_channel_id: Option<ChannelId>) -> impl Iterator<Item=&QueueEntry> {
+        self.get_shared_channel(maybe_channel_id)
+            .and_then(|channel_id| self.channels.get(&channel_id))
+            .into_iter()
+            .flat_map(move |channel| {
+                channel.entries
+                    .iter()
+                    .filter(move |channel_entry| channel_entry.user_id == user_id)
+                    .map(|channel_entry| &channel_entry.entry)
+            })
+            .chain(self.user_queue(user_id))
+    }
+
+    pub fn user_queues(&self) -> impl Iterator<Item=(UserId, impl Iterator<Item=&QueueEntry>)> {
+        self.queues
+            .iter()
+            .map(|queue| (queue.user_id, queue.entries.iter().map(|entry| &entry.entry)))
+    }
+
+    pub fn channel_playing_user(&self, channel_id: ChannelId) -> Option<UserId> {
+        match self.get_channel_playing_state(channel_id) {
+            Some(ChannelPlayingState::Playing { playing_user_id: user_id, .. }) => Some(*user_id),
+            _ => None,
+        }
+    }
+
+    pub fn playing_channels(&self) -> impl Iterator<Item=(ChannelId, UserId)> + '_ {
+        self.channels
+            .keys()
+            .filter_map(move |channel_id| self.channel_playing_user(*channel_id).map(|user_id| (*channel_id, user_id)))
+    }
+
+    /// Returns the entries that will be played in the channel, in the order they will be played,
+    /// assuming the users in the channel don't change. Policies that depend on airtime assume
+    /// each upcoming entry plays for the same amount of time.
+    pub fn upcoming_channel_entries<Delegate: AppModelDelegate>(&self, delegate: &Delegate, channel_id: ChannelId) -> Vec<UpcomingEntry<'_, QueueEntry>> {
+        // The channel's shared queue always plays first.
+        let mut upcoming_entries: Vec<_> = self.channels
+            .get(&channel_id)
+            .into_iter()
+            .flat_map(|channel| channel.entries.iter())
+            .map(|channel_entry| UpcomingEntry {
+                user_id: channel_entry.user_id,
+                entry: &channel_entry.entry,
+            })
+            .collect();
+
+        let policy = self.config.scheduling_policy.policy();
+        let mut remaining_queues: Vec<_> = self.queues
+            .iter()
+            .map(|queue| (queue.user_id, queue.entries.iter().collect::<VecDeque<_>>()))
+            .collect();
+        let mut airtime = self.airtime.clone();
+        let mut last_user_id = upcoming_entries
+            .last()
+            .map(|upcoming_entry| upcoming_entry.user_id)
+            .or_else(|| self.channel_playing_user(channel_id));
+
+        loop {
+            let queue_sequences: Vec<_> = remaining_queues
+                .iter()
+                .map(|(user_id, entries)| (*user_id, entries.iter().map(|entry| entry.sequence).min().unwrap_or(u64::MAX)))
+                .collect();
+            let next_user_id = match find_next_user(policy, &queue_sequences, &airtime, last_user_id, delegate, channel_id) {
+                Some(user_id) => user_id,
+                None => break,
+            };
+
+            let (_, next_entries) = remaining_queues
+                .iter_mut()
+                .find(|(user_id, _)| *user_id == next_user_id)
+                .unwrap();
+            if let Some(queued_entry) = next_entries.pop_front() {
+                upcoming_entries.push(UpcomingEntry {
+                    user_id: next_user_id,
+                    entry: &queued_entry.entry,
+                });
+            }
+
+            // Mirror the model removing queues once they're empty
+            remaining_queues.retain(|(_, entries)| !entries.is_empty());
+            *airtime.entry(next_user_id).or_default() += ESTIMATED_ENTRY_AIRTIME;
+            last_user_id = Some(next_user_id);
+        }
+
+        upcoming_entries
+    }
+
+    /// Entries that have played in the guild, most recent first.
+    pub fn history(&self) -> impl Iterator<Item=&PlayedEntry<QueueEntry>> {
+        self.history.iter().rev()
+    }
+
+    /// The entry at `index` in [`GuildModel::history`], where 0 is the most recent.
+    pub fn history_entry(&self, index: usize) -> Option<&PlayedEntry<QueueEntry>> {
+        self.history().nth(index)
+    }
+
+    /// Predicts the entry that [`GuildModel::next_channel_entry_finished`] will choose once the
+    /// current entry finishes. This can be wrong if queues or the people in the channel change
+    /// before then.
+    pub fn peek_next_channel_entry<Delegate: AppModelDelegate>(&self, delegate: &Delegate, channel_id: ChannelId) -> Option<&QueueEntry> {
+        if let Some(ChannelPlayingState::Playing { entry, skipped: false, .. }) = self.get_channel_playing_state(channel_id) {
+            if self.channel_repeat_mode(channel_id) == RepeatMode::Song {
+                return Some(entry);
             }
         }
+
+        self.upcoming_channel_entries(delegate, channel_id)
+            .into_iter()
+            .next()
+            .map(|upcoming_entry| upcoming_entry.entry)
     }
 
     // Events:
-    pub fn next_channel_entry_finished<Delegate: AppModelDelegate>(&mut self, delegate: &Delegate, channel_id: ChannelId) -> Option<QueueEntry> {
-        let old_playing_state = std::mem::replace(&mut self.create_channel(channel_id).playing, ChannelPlayingState::NotPlaying);
-
-        // Round-robin to the next user
-        let next_user_id = match old_playing_state {
-            ChannelPlayingState::Playing { playing_user_id: user_id, .. } => {
-                let last_playing_queue_index = self
-                    .queues
-                    .iter_mut()
-                    .position(|queue| queue.user_id == user_id);
-                match last_playing_queue_index {
-                    Some(last_playing_index) => {
-                        // Search queues from after the last active one, back around to it again
-                        let queues_iter = self.queues
-                            .iter()
-                            .skip(last_playing_index + 1)
-                            .chain(self.queues.iter().take(last_playing_index + 1));
-                        find_first_user_in_channel(queues_iter, delegate, channel_id)
+    pub fn next_channel_entry_finished<Delegate: AppModelDelegate>(&mut self, delegate: &Delegate, channel_id: ChannelId) -> Option<QueueEntry> where QueueEntry: Clone {
+        let old_playing_state = self.replace_channel_playing_state(channel_id, ChannelPlayingState::NotPlaying);
+
+        // Repeat the entry that just finished if the channel is set to, and nobody skipped it
+        let last_user_id = match old_playing_state {
+            ChannelPlayingState::Playing { playing_user_id, entry, skipped: false, .. } => {
+                match self.channel_repeat_mode(channel_id) {
+                    RepeatMode::Off => {}
+                    RepeatMode::Song => {
+                        self.set_channel_playing(channel_id, playing_user_id, entry.clone());
+                        return Some(entry);
                     }
-                    None => find_first_user_in_channel(self.queues.iter(), delegate, channel_id),
+                    // Repeated entries were already allowed in the queue, so limits aren't checked
+                    RepeatMode::Queue => self.push_entry_unchecked(playing_user_id, Some(channel_id), entry),
                 }
+                Some(playing_user_id)
             }
-            _ => find_first_user_in_channel(self.queues.iter(), delegate, channel_id),
-        }?;
-
-        let next_queue = self.get_user_queue_mut(next_user_id)?;
-        let next_entry = next_queue.entries.pop_front()?;
-
-        // Update channel state to indicate it's playing
-        self.create_channel(channel_id).playing = ChannelPlayingState::Playing {
-            playing_user_id: next_queue.user_id,
-            skip_votes: HashSet::new(),
-            stop_votes: HashSet::new(),
+            ChannelPlayingState::Playing { playing_user_id, .. } => Some(playing_user_id),
+            _ => None,
         };
 
-        // Remove any empty queues and channels
-        self.queues.retain(|queue| !queue.entries.is_empty());
-        self.channels.retain(|_, channel| channel.playing.is_playing());
+        // Entries in the channel's shared queue play before anybody's own queue
+        if let Some(channel_entry) = self.create_channel(channel_id).entries.pop_front() {
+            self.set_channel_playing(channel_id, channel_entry.user_id, channel_entry.entry.clone());
+            return Some(channel_entry.entry);
+        }
+
+        // Let the scheduling policy choose the next user
+        let queue_sequences: Vec<_> = self.queues
+            .iter()
+            .map(|queue| (queue.user_id, queue.first_queued_sequence()))
+            .collect();
+        let next_user_id = find_next_user(self.config.scheduling_policy.policy(), &queue_sequences, &self.airtime, last_user_id, delegate, channel_id)?;
 
-        Some(next_entry)
+        let next_queue = self.get_user_queue_mut(next_user_id)?;
+        let next_entry = next_queue.entries.pop_front()?;
+        self.set_channel_playing(channel_id, next_user_id, next_entry.entry.clone());
+        Some(next_entry.entry)
     }
 
-    pub fn next_channel_entry<Delegate: AppModelDelegate>(&mut self, delegate: &Delegate, channel_id: ChannelId) -> NextEntry<QueueEntry> {
+    pub fn next_channel_entry<Delegate: AppModelDelegate>(&mut self, delegate: &Delegate, channel_id: ChannelId) -> NextEntry<QueueEntry> where QueueEntry: Clone {
         match self.get_channel_playing_state(channel_id) {
             Some(ChannelPlayingState::Playing { .. }) => NextEntry::AlreadyPlaying,
             _ => match self.next_channel_entry_finished(delegate, channel_id) {
@@ -191,51 +758,206 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         }
     }
 
+    /// Ends the entry playing in a channel without repeating it, e.g. because it couldn't be
+    /// played. It's recorded as skipped in the history.
+    pub fn end_channel_entry(&mut self, channel_id: ChannelId) {
+        if let Some(ChannelPlayingState::Playing { skipped, .. }) = self.get_channel_playing_state_mut(channel_id) {
+            *skipped = true;
+            self.replace_channel_playing_state(channel_id, ChannelPlayingState::NotPlaying);
+            self.channels.retain(|_, channel| !channel.is_empty());
+        }
+    }
+
     pub fn vote_for_skip<Delegate: AppModelDelegate>(&mut self, delegate: &Delegate, vote_type: VoteType, channel_id: ChannelId, user_id: UserId) -> VoteStatus {
-        let votes_required = match vote_type {
+        let vote_threshold = match vote_type {
             VoteType::Skip => self.config.skip_votes_required,
             VoteType::Stop => self.config.stop_votes_required,
+            VoteType::Seek(_) | VoteType::Filter(_) | VoteType::Loop(_) => self.config.skip_votes_required,
         };
+        let votes_required = vote_threshold.votes_required(delegate.count_users_in_voice_channel(channel_id));
         match self.get_channel_playing_state_mut(channel_id) {
-            Some(ChannelPlayingState::Playing { playing_user_id, skip_votes, stop_votes, .. }) => {
-                let votes = match vote_type {
-                    VoteType::Skip => skip_votes,
-                    VoteType::Stop => stop_votes
-                };
-
-                // We can skip immediately if this was the user who's currently playing
-                if user_id == *playing_user_id {
-                    return VoteStatus::Success;
+            Some(ChannelPlayingState::Playing { playing_user_id, skipped, votes: all_votes, .. }) => {
+                // Voting for a different change of the same kind takes back the user's earlier
+                // vote, e.g. when they change their mind about where to seek to.
+                for (other_vote_type, other_votes) in all_votes.iter_mut() {
+                    if *other_vote_type != vote_type && std::mem::discriminant(other_vote_type) == std::mem::discriminant(&vote_type) {
+                        other_votes.remove(&user_id);
+                    }
                 }
+                let votes = all_votes.entry(vote_type).or_default();
 
-                // We can skip immediately if the user who played this entry is not in the channel
-                // anymore.
-                if !delegate.is_user_in_voice_channel(*playing_user_id, channel_id) {
-                    return VoteStatus::Success;
-                }
+                // We can skip immediately if this was the user who's currently playing, if the
+                // user who played this entry is not in the channel anymore, or if the user is
+                // privileged.
+                let is_playing_user_absent = !delegate.is_user_in_voice_channel(*playing_user_id, channel_id);
+                if user_id != *playing_user_id && !is_playing_user_absent && !delegate.is_user_privileged(user_id) {
+                    // Prevent voting if this user has already voted
+                    if votes.contains(&user_id) {
+                        return VoteStatus::AlreadyVoted;
+                    }
 
-                // Prevent voting if this user has already voted
-                if votes.contains(&user_id) {
-                    return VoteStatus::AlreadyVoted;
+                    // Add the vote and indicate more votes are needed, unless we will have the
+                    // required number of votes
+                    if votes.len() + 1 < votes_required {
+                        votes.insert(user_id);
+                        return VoteStatus::NeedsMoreVotes(votes_required - votes.len());
+                    }
                 }
 
-                // We can succeed immediately if we will have the required number of votes
-                if votes.len() + 1 >= votes_required {
-                    return VoteStatus::Success;
-                }
+                match vote_type {
+                    // Each seek or setting change needs its own set of votes
+                    VoteType::Seek(_) | VoteType::Filter(_) | VoteType::Loop(_) => {
+                        all_votes.remove(&vote_type);
+                    }
 
-                // Add the vote and indicate more votes are needed
-                votes.insert(user_id);
-                VoteStatus::NeedsMoreVotes(votes_required - votes.len())
+                    // Remember the entry was skipped so it isn't repeated
+                    VoteType::Skip | VoteType::Stop => *skipped = true,
+                }
+                VoteStatus::Success
             }
             _ => VoteStatus::NothingPlaying,
         }
     }
 
+    fn set_channel_playing(&mut self, channel_id: ChannelId, user_id: UserId, entry: QueueEntry) where QueueEntry: Clone {
+        if self.config.history_length > 0 {
+            if self.history.len() >= self.config.history_length {
+                self.history.pop_front();
+            }
+            self.history.push_back(PlayedEntry {
+                user_id,
+                channel_id,
+                started_time: SystemTime::now(),
+                skipped: false,
+                entry: entry.clone(),
+            });
+        }
+
+        self.create_channel(channel_id).playing = ChannelPlayingState::Playing {
+            playing_user_id: user_id,
+            entry,
+            started_time: Instant::now(),
+            skipped: false,
+            votes: HashMap::new(),
+        };
+
+        // Remove any empty queues and channels
+        self.queues.retain(|queue| !queue.entries.is_empty());
+        self.channels.retain(|_, channel| !channel.is_empty());
+    }
+
+    fn push_entry_unchecked(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>, entry: QueueEntry) {
+        match self.get_shared_channel(maybe_channel_id) {
+            Some(channel_id) => {
+                let QueuedEntry { sequence, entry } = self.sequence_entry(entry);
+                self.create_channel(channel_id).entries.push_back(ChannelQueuedEntry {
+                    user_id,
+                    sequence,
+                    entry,
+                });
+            }
+            None => {
+                let queued_entry = self.sequence_entry(entry);
+                self.create_user_queue(user_id).entries.push_back(queued_entry);
+            }
+        }
+    }
+
+    fn check_limits(&self, user_id: UserId, entry: &QueueEntry, maybe_replaced_entry: Option<&QueueEntry>) -> Result<(), QueueLimit> where QueueEntry: EntryDuration {
+        if let (Some(max_entry_duration), Some(duration)) = (self.config.max_entry_duration, entry.duration()) {
+            if duration > max_entry_duration {
+                return Err(QueueLimit::EntryDuration(max_entry_duration));
+            }
+        }
+
+        // Count entries in the user's own queue and anything they've added to shared queues
+        let channel_entries = self.channels
+            .values()
+            .flat_map(|channel| channel.entries.iter())
+            .filter(|channel_entry| channel_entry.user_id == user_id)
+            .map(|channel_entry| &channel_entry.entry);
+        let user_entries: Vec<_> = self.user_queue(user_id).chain(channel_entries).collect();
+        let replaced_count = maybe_replaced_entry.map_or(0, |_| 1);
+
+        if let Some(max_user_entries) = self.config.max_user_entries {
+            if user_entries.len() - replaced_count >= max_user_entries {
+                return Err(QueueLimit::UserEntries(max_user_entries));
+            }
+        }
+
+        if let Some(max_user_duration) = self.config.max_user_duration {
+            let replaced_duration = maybe_replaced_entry.and_then(|entry| entry.duration()).unwrap_or_default();
+            let queued_duration: Duration = user_entries
+                .iter()
+                .filter_map(|entry| entry.duration())
+                .sum();
+            let new_duration = queued_duration - replaced_duration + entry.duration().unwrap_or_default();
+            if new_duration > max_user_duration {
+                return Err(QueueLimit::UserDuration(max_user_duration));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_shared_channel(&self, maybe_channel_id: Option<ChannelId>) -> Option<ChannelId> {
+        maybe_channel_id.filter(|channel_id| self.channel_queue_mode(*channel_id) == QueueMode::Shared)
+    }
+
+    fn sequence_entry(&mut self, entry: QueueEntry) -> QueuedEntry<QueueEntry> {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        QueuedEntry {
+            sequence,
+            entry,
+        }
+    }
+
+    /// Changes the channel's playing state, counting the airtime of anything that was playing.
+    fn replace_channel_playing_state(&mut self, channel_id: ChannelId, new_state: ChannelPlayingState<QueueEntry>) -> ChannelPlayingState<QueueEntry> {
+        let old_state = std::mem::replace(&mut self.create_channel(channel_id).playing, new_state);
+        if let ChannelPlayingState::Playing { playing_user_id, started_time, skipped, .. } = &old_state {
+            *self.airtime.entry(*playing_user_id).or_default() += started_time.elapsed();
+
+            // The most recent history entry for the channel is the one that was playing
+            if *skipped {
+                let maybe_played_entry = self.history
+                    .iter_mut()
+                    .rev()
+                    .find(|played_entry| played_entry.channel_id == channel_id);
+                if let Some(played_entry) = maybe_played_entry {
+                    played_entry.skipped = true;
+                }
+            }
+        }
+        old_state
+    }
+
     fn get_user_queue_mut(&mut self, user_id: UserId) -> Option<&mut Queue<QueueEntry>> {
         self.queues.iter_mut().find(|queue| queue.user_id == user_id)
     }
 
+    fn user_entries_mut(&mut self, user_id: UserId, maybe_channel_id: Option<ChannelId>) -> impl Iterator<Item=&mut QueueEntry> {
+        let maybe_channel = match self.get_shared_channel(maybe_channel_id) {
+            Some(channel_id) => self.channels.get_mut(&channel_id),
+            None => None,
+        };
+        let channel_entries = maybe_channel
+            .into_iter()
+            .flat_map(move |channel| {
+                channel.entries
+                    .iter_mut()
+                    .filter(move |channel_entry| channel_entry.user_id == user_id)
+                    .map(|channel_entry| &mut channel_entry.entry)
+            });
+        let queue_entries = self.queues
+            .iter_mut()
+            .find(|queue| queue.user_id == user_id)
+            .into_iter()
+            .flat_map(|queue| queue.entries.iter_mut().map(|queued_entry| &mut queued_entry.entry));
+        channel_entries.chain(queue_entries)
+    }
+
     fn create_user_queue(&mut self, user_id: UserId) -> &mut Queue<QueueEntry> {
         // For some reason we need to get the index then lookup instead of using .find() to work
         // around the borrow checker.
@@ -250,25 +972,300 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         self.queues.last_mut().unwrap()
     }
 
-    fn create_channel(&mut self, channel_id: ChannelId) -> &mut ChannelModel {
+    fn create_channel(&mut self, channel_id: ChannelId) -> &mut ChannelModel<QueueEntry> {
         self.channels.entry(channel_id)
-            .or_insert(ChannelModel {
-                playing: ChannelPlayingState::NotPlaying
-            })
+            .or_insert_with(|| ChannelModel::new(ChannelPlayingState::NotPlaying))
     }
 
-    fn get_channel_playing_state(&self, channel_id: ChannelId) -> Option<&ChannelPlayingState> {
+    fn get_channel_playing_state(&self, channel_id: ChannelId) -> Option<&ChannelPlayingState<QueueEntry>> {
         self.channels.get(&channel_id).map(|channel| &channel.playing)
     }
 
-    fn get_channel_playing_state_mut(&mut self, channel_id: ChannelId) -> Option<&mut ChannelPlayingState> {
+    fn get_channel_playing_state_mut(&mut self, channel_id: ChannelId) -> Option<&mut ChannelPlayingState<QueueEntry>> {
         self.channels.get_mut(&channel_id).map(|channel| &mut channel.playing)
     }
+}
 
-    fn get_channel_playing_user(&self, channel_id: ChannelId) -> Option<UserId> {
-        match self.get_channel_playing_state(channel_id) {
-            Some(ChannelPlayingState::Playing { playing_user_id: user_id, .. }) => Some(*user_id),
-            _ => None,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SchedulingPolicyKind, VoteThreshold};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestEntry {
+        name: &'static str,
+        duration: Option<Duration>,
+    }
+
+    impl EntryDuration for TestEntry {
+        fn duration(&self) -> Option<Duration> {
+            self.duration
+        }
+    }
+
+    fn entry(name: &'static str) -> TestEntry {
+        TestEntry {
+            name,
+            duration: None,
+        }
+    }
+
+    fn timed_entry(name: &'static str, secs: u64) -> TestEntry {
+        TestEntry {
+            name,
+            duration: Some(Duration::from_secs(secs)),
+        }
+    }
+
+    struct TestDelegate {
+        channel_users: Vec<UserId>,
+    }
+
+    impl AppModelDelegate for TestDelegate {
+        fn is_user_in_voice_channel(&self, user_id: UserId, _channel_id: ChannelId) -> bool {
+            self.channel_users.contains(&user_id)
+        }
+
+        fn count_users_in_voice_channel(&self, _channel_id: ChannelId) -> usize {
+            self.channel_users.len()
+        }
+
+        fn is_user_privileged(&self, _user_id: UserId) -> bool {
+            false
+        }
+    }
+
+    const CHANNEL: ChannelId = ChannelId(1);
+
+    fn config() -> AppModelConfig {
+        AppModelConfig {
+            skip_votes_required: VoteThreshold::Count(3),
+            stop_votes_required: VoteThreshold::Count(3),
+            scheduling_policy: SchedulingPolicyKind::RoundRobin,
+            queue_mode: QueueMode::PerUser,
+            history_length: 10,
+            max_user_entries: None,
+            max_user_duration: None,
+            max_entry_duration: None,
+        }
+    }
+
+    fn delegate(user_ids: &[u64]) -> TestDelegate {
+        TestDelegate {
+            channel_users: user_ids.iter().copied().map(UserId).collect(),
+        }
+    }
+
+    fn names<'a>(entries: impl Iterator<Item=&'a TestEntry>) -> Vec<&'static str> {
+        entries.map(|entry| entry.name).collect()
+    }
+
+    /// Plays entries in [`CHANNEL`] until nothing is left, returning their names.
+    fn play_all(model: &mut GuildModel<TestEntry>, delegate: &TestDelegate) -> Vec<&'static str> {
+        std::iter::from_fn(|| model.next_channel_entry_finished(delegate, CHANNEL))
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    /// Creates a model where user 1's entry is playing in [`CHANNEL`].
+    fn playing_model(delegate: &TestDelegate) -> GuildModel<TestEntry> {
+        let mut model = GuildModel::new(config());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("a")).is_ok());
+        assert!(matches!(model.next_channel_entry(delegate, CHANNEL), NextEntry::Entry(_)));
+        model
+    }
+
+    #[test]
+    fn seek_votes_only_count_towards_the_same_position() {
+        let delegate = delegate(&[1, 2, 3, 4]);
+        let mut model = playing_model(&delegate);
+        let start = VoteType::Seek(Duration::from_secs(10));
+        let end = VoteType::Seek(Duration::from_secs(180));
+
+        assert!(matches!(model.vote_for_skip(&delegate, start, CHANNEL, UserId(2)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, end, CHANNEL, UserId(3)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, start, CHANNEL, UserId(4)), VoteStatus::NeedsMoreVotes(1)));
+        assert!(matches!(model.vote_for_skip(&delegate, start, CHANNEL, UserId(2)), VoteStatus::AlreadyVoted));
+
+        // Changing to the other position moves the vote instead of counting it twice
+        assert!(matches!(model.vote_for_skip(&delegate, end, CHANNEL, UserId(4)), VoteStatus::NeedsMoreVotes(1)));
+        assert!(matches!(model.vote_for_skip(&delegate, start, CHANNEL, UserId(3)), VoteStatus::NeedsMoreVotes(1)));
+        assert!(matches!(model.vote_for_skip(&delegate, start, CHANNEL, UserId(4)), VoteStatus::Success));
+
+        // Votes are used up by a successful seek
+        assert!(matches!(model.vote_for_skip(&delegate, start, CHANNEL, UserId(2)), VoteStatus::NeedsMoreVotes(2)));
+    }
+
+    #[test]
+    fn playing_user_seeks_without_votes() {
+        let delegate = delegate(&[1, 2, 3, 4]);
+        let mut model = playing_model(&delegate);
+        let seek = VoteType::Seek(Duration::from_secs(10));
+
+        assert!(matches!(model.vote_for_skip(&delegate, seek, CHANNEL, UserId(1)), VoteStatus::Success));
+    }
+
+    #[test]
+    fn filter_votes_only_count_towards_the_same_filter() {
+        let delegate = delegate(&[1, 2, 3, 4]);
+        let mut model = playing_model(&delegate);
+        let bass_boost = VoteType::Filter(Some(AudioFilter::BassBoost));
+        let nightcore = VoteType::Filter(Some(AudioFilter::Nightcore));
+        let off = VoteType::Filter(None);
+
+        assert!(matches!(model.vote_for_skip(&delegate, bass_boost, CHANNEL, UserId(2)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, bass_boost, CHANNEL, UserId(3)), VoteStatus::NeedsMoreVotes(1)));
+        assert!(matches!(model.vote_for_skip(&delegate, nightcore, CHANNEL, UserId(4)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, off, CHANNEL, UserId(4)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, bass_boost, CHANNEL, UserId(4)), VoteStatus::Success));
+    }
+
+    #[test]
+    fn loop_votes_only_count_towards_the_same_mode() {
+        let delegate = delegate(&[1, 2, 3, 4]);
+        let mut model = playing_model(&delegate);
+        let song = VoteType::Loop(RepeatMode::Song);
+        let queue = VoteType::Loop(RepeatMode::Queue);
+
+        assert!(matches!(model.vote_for_skip(&delegate, song, CHANNEL, UserId(2)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, song, CHANNEL, UserId(3)), VoteStatus::NeedsMoreVotes(1)));
+        assert!(matches!(model.vote_for_skip(&delegate, queue, CHANNEL, UserId(4)), VoteStatus::NeedsMoreVotes(2)));
+        assert!(matches!(model.vote_for_skip(&delegate, song, CHANNEL, UserId(4)), VoteStatus::Success));
+
+        // The vote for the other mode was moved, not kept
+        assert!(matches!(model.vote_for_skip(&delegate, queue, CHANNEL, UserId(2)), VoteStatus::NeedsMoreVotes(2)));
+    }
+
+    #[test]
+    fn entry_count_limit_ignores_the_replaced_entry() {
+        let mut model = GuildModel::new(AppModelConfig { max_user_entries: Some(2), ..config() });
+        assert!(model.push_entry(UserId(1), None, entry("a")).is_ok());
+        assert!(model.push_entry(UserId(1), None, entry("b")).is_ok());
+        assert!(matches!(model.push_entry(UserId(1), None, entry("c")), Err(QueueLimit::UserEntries(2))));
+        assert!(matches!(model.replace_entry(UserId(1), None, entry("c")), Ok(ReplaceStatus::ReplacedInQueue(_))));
+        assert_eq!(names(model.user_queue(UserId(1))), ["a", "c"]);
+
+        // Other users have their own limit
+        assert!(model.push_entry(UserId(2), None, entry("d")).is_ok());
+    }
+
+    #[test]
+    fn duration_limits() {
+        let mut model = GuildModel::new(AppModelConfig {
+            max_user_duration: Some(Duration::from_secs(100)),
+            max_entry_duration: Some(Duration::from_secs(60)),
+            ..config()
+        });
+        assert!(matches!(model.push_entry(UserId(1), None, timed_entry("a", 61)), Err(QueueLimit::EntryDuration(_))));
+        assert!(model.push_entry(UserId(1), None, timed_entry("a", 60)).is_ok());
+        assert!(model.push_entry(UserId(1), None, timed_entry("b", 40)).is_ok());
+        assert!(matches!(model.push_entry(UserId(1), None, timed_entry("c", 1)), Err(QueueLimit::UserDuration(_))));
+
+        // Entries without a known duration only count towards the entry limit
+        assert!(model.push_entry(UserId(1), None, entry("c")).is_ok());
+
+        // Replacing counts the new entry's duration instead of the replaced one's
+        assert!(model.replace_entry(UserId(1), None, timed_entry("d", 60)).is_err());
+        assert!(model.replace_entry(UserId(1), None, timed_entry("d", 0)).is_ok());
+    }
+
+    #[test]
+    fn round_robin_alternates_between_users() {
+        let delegate = delegate(&[1, 2]);
+        let mut model = GuildModel::new(config());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("a")).is_ok());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("b")).is_ok());
+        assert!(model.push_entry(UserId(2), Some(CHANNEL), entry("c")).is_ok());
+        assert_eq!(play_all(&mut model, &delegate), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn fifo_plays_in_the_order_entries_were_queued() {
+        let delegate = delegate(&[1, 2]);
+        let mut model = GuildModel::new(AppModelConfig { scheduling_policy: SchedulingPolicyKind::Fifo, ..config() });
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("a")).is_ok());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("b")).is_ok());
+        assert!(model.push_entry(UserId(2), Some(CHANNEL), entry("c")).is_ok());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("d")).is_ok());
+        assert_eq!(play_all(&mut model, &delegate), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn users_outside_the_channel_are_skipped() {
+        let delegate = delegate(&[2]);
+        let mut model = GuildModel::new(config());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("a")).is_ok());
+        assert!(model.push_entry(UserId(2), Some(CHANNEL), entry("b")).is_ok());
+        assert_eq!(play_all(&mut model, &delegate), ["b"]);
+        assert_eq!(names(model.user_queue(UserId(1))), ["a"]);
+    }
+
+    #[test]
+    fn repeat_song_plays_the_entry_again_until_skipped() {
+        let delegate = delegate(&[1]);
+        let mut model = playing_model(&delegate);
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("b")).is_ok());
+        model.set_channel_repeat_mode(CHANNEL, RepeatMode::Song);
+
+        assert_eq!(model.next_channel_entry_finished(&delegate, CHANNEL).map(|entry| entry.name), Some("a"));
+        assert_eq!(model.next_channel_entry_finished(&delegate, CHANNEL).map(|entry| entry.name), Some("a"));
+        assert!(matches!(model.vote_for_skip(&delegate, VoteType::Skip, CHANNEL, UserId(1)), VoteStatus::Success));
+        assert_eq!(model.next_channel_entry_finished(&delegate, CHANNEL).map(|entry| entry.name), Some("b"));
+    }
+
+    #[test]
+    fn repeat_queue_puts_entries_back_at_the_end() {
+        let delegate = delegate(&[1]);
+        let mut model = playing_model(&delegate);
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("b")).is_ok());
+        model.set_channel_repeat_mode(CHANNEL, RepeatMode::Queue);
+
+        assert_eq!(model.next_channel_entry_finished(&delegate, CHANNEL).map(|entry| entry.name), Some("b"));
+        assert_eq!(names(model.user_queue(UserId(1))), ["a"]);
+        assert!(matches!(model.vote_for_skip(&delegate, VoteType::Skip, CHANNEL, UserId(1)), VoteStatus::Success));
+        assert_eq!(model.next_channel_entry_finished(&delegate, CHANNEL).map(|entry| entry.name), Some("a"));
+
+        // Skipped entries aren't put back
+        assert_eq!(names(model.user_queue(UserId(1))), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn move_entry_reorders_the_users_queue() {
+        let mut model = GuildModel::new(config());
+        for name in ["a", "b", "c"] {
+            assert!(model.push_entry(UserId(1), None, entry(name)).is_ok());
+        }
+
+        assert_eq!(model.move_entry(UserId(1), None, 0, 2).map(|entry| entry.name), Some("a"));
+        assert_eq!(names(model.user_queue(UserId(1))), ["b", "c", "a"]);
+        assert_eq!(model.move_entry(UserId(1), None, 0, 10).map(|entry| entry.name), Some("b"));
+        assert_eq!(names(model.user_queue(UserId(1))), ["c", "a", "b"]);
+        assert!(model.move_entry(UserId(1), None, 3, 0).is_none());
+    }
+
+    #[test]
+    fn remove_entry_removes_by_position() {
+        let mut model = GuildModel::new(config());
+        for name in ["a", "b", "c"] {
+            assert!(model.push_entry(UserId(1), None, entry(name)).is_ok());
         }
+
+        assert_eq!(model.remove_entry(UserId(1), None, 1).map(|entry| entry.name), Some("b"));
+        assert!(model.remove_entry(UserId(1), None, 2).is_none());
+        assert_eq!(names(model.user_queue(UserId(1))), ["a", "c"]);
+        assert_eq!(model.clear_entries(UserId(1), None), 2);
+        assert_eq!(model.user_queues().count(), 0);
+    }
+
+    #[test]
+    fn shared_queue_entries_come_before_the_users_own_queue() {
+        let mut model = GuildModel::new(config());
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("a")).is_ok());
+        model.set_channel_queue_mode(CHANNEL, Some(QueueMode::Shared));
+        assert!(model.push_entry(UserId(1), Some(CHANNEL), entry("b")).is_ok());
+        assert_eq!(names(model.user_entries(UserId(1), Some(CHANNEL))), ["b", "a"]);
+
+        assert_eq!(model.remove_entry(UserId(1), Some(CHANNEL), 1).map(|entry| entry.name), Some("a"));
+        assert_eq!(names(model.user_entries(UserId(1), Some(CHANNEL))), ["b"]);
     }
 }
@@ -1,9 +1,15 @@
 mod app_model;
 mod app_model_delegate;
+mod audio_filter;
 mod config;
+mod entry_duration;
 mod guild_model;
+mod scheduling_policy;
 
 pub use self::app_model::*;
 pub use self::app_model_delegate::*;
+pub use self::audio_filter::*;
 pub use self::config::*;
+pub use self::entry_duration::*;
 pub use self::guild_model::*;
+pub use self::scheduling_policy::*;
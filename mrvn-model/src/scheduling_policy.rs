@@ -0,0 +1,143 @@
+use serenity::model::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::time::Duration;
+
+/// A user with songs queued, as seen by a [`SchedulingPolicy`] choosing who plays next.
+pub struct SchedulingCandidate {
+    pub user_id: UserId,
+
+    /// Users who aren't in the channel can't be picked, but are still provided so policies can
+    /// use their position.
+    pub is_in_channel: bool,
+
+    /// Orders the oldest entry in the user's queue against entries queued by other users. Lower
+    /// values were queued first.
+    pub first_queued_sequence: u64,
+
+    /// How long songs queued by the user have played for in the guild.
+    pub airtime: Duration,
+}
+
+pub trait SchedulingPolicy: Send + Sync {
+    /// Chooses the user whose next entry should play in a channel. Candidates are provided in the
+    /// order their queues were created, and `last_user_id` is the user whose entry just finished
+    /// playing in the channel, if any.
+    fn next_user(&self, candidates: &[SchedulingCandidate], last_user_id: Option<UserId>) -> Option<UserId>;
+}
+
+/// Alternates between users in the channel, so everybody gets a go.
+pub struct RoundRobinPolicy;
+
+impl SchedulingPolicy for RoundRobinPolicy {
+    fn next_user(&self, candidates: &[SchedulingCandidate], last_user_id: Option<UserId>) -> Option<UserId> {
+        // Search queues from after the last active one, back around to it again. If the last
+        // active user doesn't have a queue anymore, search from the start.
+        let start_index = last_user_id
+            .and_then(|last_user_id| candidates.iter().position(|candidate| candidate.user_id == last_user_id))
+            .map(|last_index| last_index + 1)
+            .unwrap_or(0);
+        candidates
+            .iter()
+            .skip(start_index)
+            .chain(candidates.iter().take(start_index))
+            .find(|candidate| candidate.is_in_channel)
+            .map(|candidate| candidate.user_id)
+    }
+}
+
+/// Plays entries in the order they were queued, regardless of who queued them.
+pub struct FifoPolicy;
+
+impl SchedulingPolicy for FifoPolicy {
+    fn next_user(&self, candidates: &[SchedulingCandidate], _last_user_id: Option<UserId>) -> Option<UserId> {
+        candidates
+            .iter()
+            .filter(|candidate| candidate.is_in_channel)
+            .min_by_key(|candidate| candidate.first_queued_sequence)
+            .map(|candidate| candidate.user_id)
+    }
+}
+
+/// Picks the user whose songs have played for the least time, so people who queue short songs
+/// get more turns than people who queue long ones. Ties go to whoever queued first.
+pub struct FairAirtimePolicy;
+
+impl SchedulingPolicy for FairAirtimePolicy {
+    fn next_user(&self, candidates: &[SchedulingCandidate], _last_user_id: Option<UserId>) -> Option<UserId> {
+        candidates
+            .iter()
+            .filter(|candidate| candidate.is_in_channel)
+            .min_by_key(|candidate| (candidate.airtime, candidate.first_queued_sequence))
+            .map(|candidate| candidate.user_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingPolicyKind {
+    #[default]
+    RoundRobin,
+    Fifo,
+    FairAirtime,
+}
+
+impl SchedulingPolicyKind {
+    pub fn policy(self) -> &'static dyn SchedulingPolicy {
+        match self {
+            SchedulingPolicyKind::RoundRobin => &RoundRobinPolicy,
+            SchedulingPolicyKind::Fifo => &FifoPolicy,
+            SchedulingPolicyKind::FairAirtime => &FairAirtimePolicy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(user_id: u64, is_in_channel: bool, first_queued_sequence: u64, airtime_secs: u64) -> SchedulingCandidate {
+        SchedulingCandidate {
+            user_id: UserId(user_id),
+            is_in_channel,
+            first_queued_sequence,
+            airtime: Duration::from_secs(airtime_secs),
+        }
+    }
+
+    #[test]
+    fn round_robin_continues_after_the_last_user() {
+        let candidates = [candidate(1, true, 0, 0), candidate(2, false, 1, 0), candidate(3, true, 2, 0)];
+        assert_eq!(RoundRobinPolicy.next_user(&candidates, None), Some(UserId(1)));
+        assert_eq!(RoundRobinPolicy.next_user(&candidates, Some(UserId(1))), Some(UserId(3)));
+        assert_eq!(RoundRobinPolicy.next_user(&candidates, Some(UserId(3))), Some(UserId(1)));
+
+        // The last user's queue may have emptied since
+        assert_eq!(RoundRobinPolicy.next_user(&candidates, Some(UserId(4))), Some(UserId(1)));
+    }
+
+    #[test]
+    fn fifo_picks_the_oldest_entry_in_the_channel() {
+        let candidates = [candidate(1, true, 5, 0), candidate(2, false, 1, 0), candidate(3, true, 2, 0)];
+        assert_eq!(FifoPolicy.next_user(&candidates, Some(UserId(3))), Some(UserId(3)));
+    }
+
+    #[test]
+    fn fair_airtime_picks_the_user_with_the_least_airtime() {
+        let candidates = [candidate(1, true, 0, 300), candidate(2, true, 1, 120), candidate(3, false, 2, 0)];
+        assert_eq!(FairAirtimePolicy.next_user(&candidates, None), Some(UserId(2)));
+    }
+
+    #[test]
+    fn fair_airtime_ties_go_to_whoever_queued_first() {
+        let candidates = [candidate(1, true, 4, 60), candidate(2, true, 3, 60)];
+        assert_eq!(FairAirtimePolicy.next_user(&candidates, None), Some(UserId(2)));
+    }
+
+    #[test]
+    fn users_outside_the_channel_are_never_picked() {
+        let candidates = [candidate(1, false, 0, 0), candidate(2, false, 1, 0)];
+        for kind in [SchedulingPolicyKind::RoundRobin, SchedulingPolicyKind::Fifo, SchedulingPolicyKind::FairAirtime] {
+            assert_eq!(kind.policy().next_user(&candidates, None), None);
+        }
+    }
+}